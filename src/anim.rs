@@ -1,140 +1,1050 @@
 use {
-    color_eyre::eyre::Result,
+    crate::zoom::ZoomView,
+    color_eyre::eyre::{self, Result},
     image::{
-        AnimationDecoder, DynamicImage, Frame,
-        codecs::{gif::GifDecoder, webp::WebPDecoder},
+        AnimationDecoder, DynamicImage, Frame, Frames,
+        codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+        imageops::{self, FilterType},
+    },
+    ratatui::prelude::{Buffer, Rect},
+    ratatui_image::{Resize, ResizeEncodeRender, picker::Picker, protocol::StatefulProtocol},
+    std::{
+        collections::BTreeMap,
+        io::Cursor,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+            mpsc::{self, Receiver, Sender, SyncSender, TryRecvError},
+        },
+        thread,
+        time::{Duration, Instant},
     },
-    ratatui_image::{ResizeEncodeRender, picker::Picker, protocol::StatefulProtocol},
-    rayon::prelude::*,
-    std::{io::Cursor, time::Instant},
 };
 
-pub enum ImageProtocol {
-    Single(StatefulProtocol),
-    Animated {
-        frames: Vec<StatefulProtocol>,
-        current: usize,
-        last_change: Instant,
-        frame_delay_ms: u64,
-    },
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const CLAMPED_FRAME_DELAY: Duration = Duration::from_millis(100);
+const LOOKAHEAD_FRAMES: usize = 4;
+const MIN_WINDOW_FRAMES: usize = 8;
+pub const DEFAULT_ANIMATION_MEMORY_CAP: usize = 256 * 1024 * 1024;
+pub const PLAYBACK_SPEEDS: [f64; 7] = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 4.0];
+const DEFAULT_SPEED_INDEX: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopCount {
+    Infinite,
+    Finite(u32),
 }
 
-impl ImageProtocol {
-    pub fn try_advance(&mut self) -> bool {
-        if let ImageProtocol::Animated {
-            frames,
-            current,
-            last_change,
-            frame_delay_ms,
-        } = self
+/// Clones share one memory budget, so every animation decoded for the same settings counts
+/// against the same cap.
+#[derive(Debug, Clone)]
+pub struct AnimationOptions {
+    pub memory_cap_bytes: usize,
+    in_use: Arc<AtomicUsize>,
+}
+
+impl AnimationOptions {
+    pub fn new(memory_cap_bytes: usize) -> Self {
+        Self {
+            memory_cap_bytes,
+            in_use: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self::new(DEFAULT_ANIMATION_MEMORY_CAP)
+    }
+}
+
+struct DecodedFrame {
+    delay: Duration,
+    bytes: usize,
+    protocol: StatefulProtocol,
+}
+
+enum DecoderMessage {
+    Frame(usize, Box<DecodedFrame>),
+    End(usize),
+    Failed(String),
+}
+
+enum DecoderCommand {
+    Seek(usize),
+    Area(Rect),
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackStatus {
+    pub frame: usize,
+    pub frame_count: Option<usize>,
+    pub paused: bool,
+    pub speed: f64,
+}
+
+pub struct Animation {
+    frames_rx: Receiver<DecoderMessage>,
+    commands_tx: Sender<DecoderCommand>,
+    window: BTreeMap<usize, DecodedFrame>,
+    window_bytes: usize,
+    memory_cap: usize,
+    in_use: Arc<AtomicUsize>,
+    frame_count: Option<usize>,
+    current: Option<usize>,
+    last_change: Instant,
+    loop_count: LoopCount,
+    completed_loops: u32,
+    area: Option<Rect>,
+    decoder_stopped: bool,
+    error: Option<String>,
+    paused_at: Option<Instant>,
+    speed_index: usize,
+    pending_seek: Option<usize>,
+}
+
+impl Animation {
+    fn spawn(bytes: Arc<[u8]>, picker: Picker, options: AnimationOptions) -> Self {
+        let (frames_tx, frames_rx) = mpsc::sync_channel(LOOKAHEAD_FRAMES);
+        let (commands_tx, commands_rx) = mpsc::channel();
+        let loop_count = loop_count(&bytes);
+        let frame_count = count_frames(&bytes);
+        let memory_cap = options.memory_cap_bytes;
+
+        thread::spawn(move || decode_frames(bytes, picker, memory_cap, commands_rx, frames_tx));
+
+        Self {
+            frames_rx,
+            commands_tx,
+            window: BTreeMap::new(),
+            window_bytes: 0,
+            memory_cap,
+            in_use: options.in_use,
+            frame_count,
+            current: None,
+            last_change: Instant::now(),
+            loop_count,
+            completed_loops: 0,
+            area: None,
+            decoder_stopped: false,
+            error: None,
+            paused_at: None,
+            speed_index: DEFAULT_SPEED_INDEX,
+            pending_seek: None,
+        }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.loop_count, LoopCount::Finite(plays) if self.completed_loops >= plays)
+    }
+
+    fn frames_ahead(&self) -> usize {
+        let Some(current) = self.current else {
+            return 0;
+        };
+
+        let mut ahead = 0;
+        let mut index = current;
+        while ahead < LOOKAHEAD_FRAMES {
+            index += 1;
+            if self.frame_count == Some(index) {
+                index = 0;
+            }
+            if index == current || !self.window.contains_key(&index) {
+                break;
+            }
+            ahead += 1;
+        }
+        ahead
+    }
+
+    fn receive_frames(&mut self) {
+        if self.decoder_stopped {
+            while self.frames_rx.try_recv().is_ok() {}
+            return;
+        }
+
+        while self.current.is_none()
+            || self
+                .pending_seek
+                .is_some_and(|target| !self.window.contains_key(&target))
+            || self.frames_ahead() < LOOKAHEAD_FRAMES
         {
-            if frames.is_empty() {
-                return false;
+            match self.frames_rx.try_recv() {
+                Ok(DecoderMessage::Frame(index, frame)) => self.insert(index, *frame),
+                Ok(DecoderMessage::End(frame_count)) => {
+                    self.frame_count = Some(frame_count);
+                    self.stop_when_complete();
+                }
+                Ok(DecoderMessage::Failed(error)) => {
+                    self.error = Some(error);
+                    break;
+                }
+                Err(_) => break,
+            }
+
+            if self.current.is_none() && self.window.contains_key(&0) {
+                break;
             }
+        }
+    }
 
-            let now = Instant::now();
-            let elapsed_ms = now.duration_since(*last_change).as_millis() as u64;
+    fn insert(&mut self, index: usize, frame: DecodedFrame) {
+        let added = frame.bytes;
+        let replaced = self.window.insert(index, frame).map_or(0, |old| old.bytes);
+        self.track_bytes(added, replaced);
 
-            if elapsed_ms >= *frame_delay_ms {
-                let steps = (elapsed_ms / *frame_delay_ms) as usize;
-                *current = (*current + steps) % frames.len();
-                *last_change = now - std::time::Duration::from_millis(elapsed_ms % *frame_delay_ms);
+        self.evict();
+        self.stop_when_complete();
+    }
+
+    fn track_bytes(&mut self, added: usize, removed: usize) {
+        self.window_bytes = self.window_bytes + added - removed;
+        self.in_use.fetch_add(added, Ordering::Relaxed);
+        self.in_use.fetch_sub(removed, Ordering::Relaxed);
+    }
+
+    fn evict(&mut self) {
+        let others = self
+            .in_use
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.window_bytes);
+        let budget = self.memory_cap.saturating_sub(others).saturating_sub(
+            self.window
+                .values()
+                .next()
+                .map(|f| f.bytes * LOOKAHEAD_FRAMES)
+                .unwrap_or(0),
+        );
+
+        while self.window_bytes > budget && self.window.len() > 2 {
+            let current = self.pending_seek.or(self.current).unwrap_or(0);
+            let count = self
+                .frame_count
+                .unwrap_or_else(|| self.window.keys().max().map_or(1, |max| max + 1))
+                .max(current + 1);
+
+            let Some(&victim) = self
+                .window
+                .keys()
+                .filter(|&&index| index != current)
+                .max_by_key(|&&index| (index + count - current) % count)
+            else {
+                break;
+            };
+
+            if let Some(frame) = self.window.remove(&victim) {
+                self.track_bytes(0, frame.bytes);
+            }
+        }
+    }
+
+    fn stop_when_complete(&mut self) {
+        if !self.decoder_stopped && self.frame_count == Some(self.window.len()) {
+            let _ = self.commands_tx.send(DecoderCommand::Stop);
+            self.decoder_stopped = true;
+        }
+    }
+
+    fn next_index(&self, index: usize) -> Option<usize> {
+        match self.frame_count {
+            Some(1) => None,
+            Some(count) if index + 1 >= count => Some(0),
+            _ => Some(index + 1),
+        }
+    }
+
+    pub fn try_advance(&mut self) -> bool {
+        self.receive_frames();
+
+        let Some(current) = self.current else {
+            if self.window.contains_key(&0) {
+                self.current = Some(0);
+                self.last_change = Instant::now();
                 return true;
             }
+            return false;
+        };
+
+        if let Some(mut target) = self.pending_seek {
+            // Steps taken before the first pass finished may overshoot the real frame count.
+            if let Some(count) = self.frame_count.filter(|&count| count > 0) {
+                target %= count;
+            }
+            if !self.window.contains_key(&target) {
+                return false;
+            }
+            self.current = Some(target);
+            self.pending_seek = None;
+            self.last_change = Instant::now();
+            self.receive_frames();
+            return true;
         }
-        false
+
+        if self.paused_at.is_some() || self.is_finished() {
+            return false;
+        }
+
+        let Some(delay) = self
+            .window
+            .get(&current)
+            .map(|f| f.delay.div_f64(self.speed()))
+        else {
+            return false;
+        };
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_change);
+        if elapsed < delay {
+            return false;
+        }
+
+        let Some(next) = self.next_index(current) else {
+            return false;
+        };
+
+        let wraps = next == 0;
+        if wraps
+            && matches!(self.loop_count, LoopCount::Finite(plays) if self.completed_loops + 1 >= plays)
+        {
+            self.completed_loops += 1;
+            return false;
+        }
+
+        if !self.window.contains_key(&next) {
+            return false;
+        }
+
+        if wraps {
+            self.completed_loops += 1;
+        }
+        self.current = Some(next);
+        self.last_change = if elapsed >= delay * 2 {
+            now
+        } else {
+            self.last_change + delay
+        };
+        self.receive_frames();
+        true
+    }
+
+    pub fn has_completed_loop(&self) -> bool {
+        self.completed_loops > 0 || self.error.is_some() || matches!(self.frame_count, Some(0 | 1))
+    }
+
+    pub fn speed(&self) -> f64 {
+        PLAYBACK_SPEEDS[self.speed_index]
+    }
+
+    pub fn toggle_pause(&mut self) {
+        match self.paused_at.take() {
+            Some(paused_at) => self.last_change += paused_at.elapsed(),
+            None if self.is_finished() => {
+                self.completed_loops = 0;
+                self.seek(0);
+            }
+            None => self.paused_at = Some(Instant::now()),
+        }
+    }
+
+    pub fn change_speed(&mut self, faster: bool) {
+        self.speed_index = if faster {
+            (self.speed_index + 1).min(PLAYBACK_SPEEDS.len() - 1)
+        } else {
+            self.speed_index.saturating_sub(1)
+        };
+    }
+
+    pub fn step(&mut self, offset: isize) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(Instant::now());
+        }
+
+        let Some(current) = self.pending_seek.or(self.current) else {
+            return;
+        };
+
+        let target = match self.frame_count.filter(|&count| count > 0) {
+            Some(count) => (current as isize + offset).rem_euclid(count as isize) as usize,
+            None => (current as isize + offset).max(0) as usize,
+        };
+        self.seek(target);
+    }
+
+    pub fn seek(&mut self, target: usize) {
+        if self.window.contains_key(&target) {
+            self.current = Some(target);
+            self.pending_seek = None;
+            self.last_change = Instant::now();
+            return;
+        }
+
+        self.pending_seek = Some(target);
+        if self.decoder_stopped {
+            return;
+        }
+        let _ = self.commands_tx.send(DecoderCommand::Seek(target));
+    }
+
+    pub fn status(&self) -> Option<PlaybackStatus> {
+        Some(PlaybackStatus {
+            frame: self.pending_seek.or(self.current)?,
+            frame_count: self.frame_count,
+            paused: self.paused_at.is_some(),
+            speed: self.speed(),
+        })
     }
 
-    pub fn current_protocol_mut(&mut self) -> &mut StatefulProtocol {
+    pub fn set_area(&mut self, area: Rect) {
+        if self.area != Some(area) {
+            self.area = Some(area);
+            let _ = self.commands_tx.send(DecoderCommand::Area(area));
+        }
+    }
+
+    pub fn current_protocol_mut(&mut self) -> Option<&mut StatefulProtocol> {
+        let current = self.current?;
+        self.window.get_mut(&current).map(|f| &mut f.protocol)
+    }
+}
+
+impl Drop for Animation {
+    fn drop(&mut self) {
+        let _ = self.commands_tx.send(DecoderCommand::Stop);
+        self.in_use.fetch_sub(self.window_bytes, Ordering::Relaxed);
+    }
+}
+
+fn open_frames(bytes: &Arc<[u8]>) -> Result<Frames<'static>> {
+    let cursor = Cursor::new(bytes.clone());
+
+    if bytes.starts_with(b"GIF8") {
+        return Ok(GifDecoder::new(cursor)?.into_frames());
+    }
+
+    if is_webp(bytes) {
+        return Ok(WebPDecoder::new(cursor)?.into_frames());
+    }
+
+    if bytes.starts_with(PNG_SIGNATURE) {
+        return Ok(PngDecoder::new(cursor)?.apng()?.into_frames());
+    }
+
+    eyre::bail!("Unsupported animation format")
+}
+
+fn decode_frames(
+    bytes: Arc<[u8]>,
+    picker: Picker,
+    memory_cap: usize,
+    commands_rx: Receiver<DecoderCommand>,
+    frames_tx: SyncSender<DecoderMessage>,
+) {
+    let mut start = 0;
+    let mut area = None;
+    let mut scaled_size: Option<(u32, u32)> = None;
+
+    'decode: loop {
+        let frames = match open_frames(&bytes) {
+            Ok(frames) => frames,
+            Err(e) => {
+                let _ = frames_tx.send(DecoderMessage::Failed(e.to_string()));
+                return;
+            }
+        };
+
+        let mut frame_count = 0;
+        for (index, frame) in frames.enumerate() {
+            loop {
+                match commands_rx.try_recv() {
+                    Ok(DecoderCommand::Stop) | Err(TryRecvError::Disconnected) => return,
+                    Ok(DecoderCommand::Area(new_area)) => area = Some(new_area),
+                    Ok(DecoderCommand::Seek(target)) => {
+                        start = target;
+                        continue 'decode;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                }
+            }
+
+            let frame = match frame {
+                Ok(frame) => frame,
+                Err(e) => {
+                    let _ = frames_tx.send(DecoderMessage::Failed(e.to_string()));
+                    return;
+                }
+            };
+            frame_count = index + 1;
+
+            if index < start {
+                continue;
+            }
+
+            let delay = frame_delay(&frame);
+            let mut buffer = frame.into_buffer();
+
+            let (width, height) = *scaled_size
+                .get_or_insert_with(|| fit_to_memory(buffer.width(), buffer.height(), memory_cap));
+            if (width, height) != buffer.dimensions() {
+                buffer = imageops::resize(&buffer, width, height, FilterType::Triangle);
+            }
+
+            let bytes = width as usize * height as usize * 4;
+            let mut protocol = picker.new_resize_protocol(DynamicImage::ImageRgba8(buffer));
+            if let Some(area) = area {
+                protocol.resize_encode(&Resize::Fit(None), area);
+            }
+
+            let frame = DecodedFrame {
+                delay,
+                bytes,
+                protocol,
+            };
+            if frames_tx
+                .send(DecoderMessage::Frame(index, Box::new(frame)))
+                .is_err()
+            {
+                return;
+            }
+        }
+
+        start = 0;
+        if frames_tx.send(DecoderMessage::End(frame_count)).is_err() {
+            return;
+        }
+    }
+}
+
+fn fit_to_memory(width: u32, height: u32, memory_cap: usize) -> (u32, u32) {
+    let frame_bytes = width as usize * height as usize * 4;
+    let window_bytes = frame_bytes * (MIN_WINDOW_FRAMES + LOOKAHEAD_FRAMES);
+    if window_bytes <= memory_cap || frame_bytes == 0 {
+        return (width, height);
+    }
+
+    let scale = (memory_cap as f64 / window_bytes as f64).sqrt();
+    (
+        ((width as f64 * scale) as u32).max(1),
+        ((height as f64 * scale) as u32).max(1),
+    )
+}
+
+pub enum ImageProtocol {
+    Single(StatefulProtocol, Box<ZoomView>),
+    Animated(Animation),
+}
+
+impl ImageProtocol {
+    pub fn try_advance(&mut self) -> bool {
+        match self {
+            ImageProtocol::Single(..) => false,
+            ImageProtocol::Animated(animation) => animation.try_advance(),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        match self {
+            ImageProtocol::Single(..) => true,
+            ImageProtocol::Animated(animation) => animation
+                .current
+                .is_some_and(|current| animation.window.contains_key(&current)),
+        }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            ImageProtocol::Single(..) => None,
+            ImageProtocol::Animated(animation) => animation.error(),
+        }
+    }
+
+    pub fn animation_mut(&mut self) -> Option<&mut Animation> {
+        match self {
+            ImageProtocol::Single(..) => None,
+            ImageProtocol::Animated(animation) => Some(animation),
+        }
+    }
+
+    pub fn zoom_mut(&mut self) -> Option<&mut ZoomView> {
+        match self {
+            ImageProtocol::Single(_, zoom) => Some(zoom),
+            ImageProtocol::Animated(_) => None,
+        }
+    }
+
+    pub fn has_completed_loop(&self) -> bool {
+        match self {
+            ImageProtocol::Single(..) => true,
+            ImageProtocol::Animated(animation) => animation.has_completed_loop(),
+        }
+    }
+
+    pub fn playback_status(&self) -> Option<PlaybackStatus> {
+        match self {
+            ImageProtocol::Single(..) => None,
+            ImageProtocol::Animated(animation) => animation.status(),
+        }
+    }
+
+    pub fn set_area(&mut self, area: Rect) {
+        if let ImageProtocol::Animated(animation) = self {
+            animation.set_area(area);
+        }
+    }
+
+    pub fn current_protocol_mut(&mut self) -> Option<&mut StatefulProtocol> {
         match self {
-            ImageProtocol::Single(p) => p,
-            ImageProtocol::Animated {
-                frames, current, ..
-            } => &mut frames[*current],
+            ImageProtocol::Single(p, _) => Some(p),
+            ImageProtocol::Animated(animation) => animation.current_protocol_mut(),
         }
     }
 }
 
 impl ResizeEncodeRender for ImageProtocol {
-    fn render(&mut self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer) {
-        match self {
-            ImageProtocol::Single(p) => p.render(area, buf),
-            ImageProtocol::Animated {
-                frames, current, ..
-            } => frames[*current].render(area, buf),
+    fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        if let Some(protocol) = self.current_protocol_mut() {
+            protocol.render(area, buf);
         }
     }
 
-    fn resize_encode(&mut self, resize: &ratatui_image::Resize, area: ratatui::prelude::Rect) {
+    fn resize_encode(&mut self, resize: &Resize, area: Rect) {
+        self.set_area(area);
+        if let Some(protocol) = self.current_protocol_mut() {
+            protocol.resize_encode(resize, area);
+        }
+    }
+
+    fn needs_resize(&self, resize: &Resize, area: Rect) -> Option<Rect> {
         match self {
-            ImageProtocol::Single(p) => p.resize_encode(resize, area),
-            ImageProtocol::Animated { frames, .. } => {
-                for frame in frames {
-                    if frame.needs_resize(resize, area).is_some() {
-                        frame.resize_encode(resize, area);
-                    }
+            ImageProtocol::Single(p, _) => p.needs_resize(resize, area),
+            ImageProtocol::Animated(animation) => animation
+                .current
+                .and_then(|current| animation.window.get(&current))
+                .and_then(|f| f.protocol.needs_resize(resize, area)),
+        }
+    }
+}
+
+fn is_webp(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP"
+}
+
+fn is_animated(bytes: &[u8]) -> bool {
+    let cursor = Cursor::new(bytes);
+
+    if bytes.starts_with(b"GIF8") {
+        return match count_gif_frames(bytes) {
+            Some(frames) => frames > 1,
+            None => GifDecoder::new(cursor)
+                .is_ok_and(|decoder| decoder.into_frames().take(2).count() > 1),
+        };
+    }
+
+    if is_webp(bytes) {
+        return WebPDecoder::new(cursor).is_ok_and(|decoder| decoder.has_animation());
+    }
+
+    if bytes.starts_with(PNG_SIGNATURE) {
+        return PngDecoder::new(cursor).is_ok_and(|decoder| decoder.is_apng().unwrap_or(false));
+    }
+
+    false
+}
+
+pub fn frame_delay(frame: &Frame) -> Duration {
+    let (numer, denom) = frame.delay().numer_denom_ms();
+    let delay = Duration::from_micros(numer as u64 * 1000 / denom.max(1) as u64);
+
+    if delay < MIN_FRAME_DELAY {
+        CLAMPED_FRAME_DELAY
+    } else {
+        delay
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+pub fn loop_count(bytes: &[u8]) -> LoopCount {
+    if bytes.starts_with(b"GIF8") {
+        let netscape = find(bytes, b"NETSCAPE2.0").or_else(|| find(bytes, b"ANIMEXTS1.0"));
+        return match netscape.and_then(|i| bytes.get(i + 11..i + 15)) {
+            Some(&[3, 1, lo, hi]) => match u16::from_le_bytes([lo, hi]) {
+                0 => LoopCount::Infinite,
+                n => LoopCount::Finite(n as u32 + 1),
+            },
+            _ => LoopCount::Finite(1),
+        };
+    }
+
+    if is_webp(bytes) {
+        let mut pos = 12;
+        while let Some(header) = bytes.get(pos..pos + 8) {
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+            if &header[0..4] == b"ANIM"
+                && let Some(&[lo, hi]) = bytes.get(pos + 12..pos + 14)
+            {
+                return match u16::from_le_bytes([lo, hi]) {
+                    0 => LoopCount::Infinite,
+                    n => LoopCount::Finite(n as u32),
+                };
+            }
+            pos += 8 + size + size % 2;
+        }
+    }
+
+    if bytes.starts_with(PNG_SIGNATURE) {
+        let mut pos = PNG_SIGNATURE.len();
+        while let Some(header) = bytes.get(pos..pos + 8) {
+            let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+            if &header[4..8] == b"acTL"
+                && let Some(plays) = bytes.get(pos + 12..pos + 16)
+            {
+                return match u32::from_be_bytes([plays[0], plays[1], plays[2], plays[3]]) {
+                    0 => LoopCount::Infinite,
+                    n => LoopCount::Finite(n),
+                };
+            }
+            if &header[4..8] == b"IDAT" {
+                break;
+            }
+            pos += 12 + size;
+        }
+    }
+
+    LoopCount::Infinite
+}
+
+fn count_gif_frames(bytes: &[u8]) -> Option<usize> {
+    let skip_sub_blocks = |mut pos: usize| -> Option<usize> {
+        loop {
+            let len = *bytes.get(pos)? as usize;
+            pos += 1 + len;
+            if len == 0 {
+                return Some(pos);
+            }
+        }
+    };
+
+    let packed = *bytes.get(10)?;
+    let mut pos = 13;
+    if packed & 0x80 != 0 {
+        pos += 3 << ((packed & 0x07) + 1);
+    }
+
+    let mut frames = 0;
+    loop {
+        match *bytes.get(pos)? {
+            0x21 => pos = skip_sub_blocks(pos + 2)?,
+            0x2C => {
+                let packed = *bytes.get(pos + 9)?;
+                pos += 10;
+                if packed & 0x80 != 0 {
+                    pos += 3 << ((packed & 0x07) + 1);
                 }
+                pos = skip_sub_blocks(pos + 1)?;
+                frames += 1;
             }
+            0x3B => return Some(frames),
+            _ => return None,
         }
     }
+}
 
-    fn needs_resize(
-        &self,
-        resize: &ratatui_image::Resize,
-        area: ratatui::prelude::Rect,
-    ) -> Option<ratatui::prelude::Rect> {
-        match self {
-            ImageProtocol::Single(p) => p.needs_resize(resize, area),
-            ImageProtocol::Animated { frames, .. } => {
-                frames.first().and_then(|f| f.needs_resize(resize, area))
+pub fn count_frames(bytes: &[u8]) -> Option<usize> {
+    if bytes.starts_with(b"GIF8") {
+        return count_gif_frames(bytes);
+    }
+
+    if is_webp(bytes) {
+        let mut frames = 0;
+        let mut pos = 12;
+        while let Some(header) = bytes.get(pos..pos + 8) {
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+            if &header[0..4] == b"ANMF" {
+                frames += 1;
             }
+            pos += 8 + size + size % 2;
         }
+        return (frames > 0).then_some(frames);
     }
+
+    if bytes.starts_with(PNG_SIGNATURE) {
+        let mut pos = PNG_SIGNATURE.len();
+        while let Some(header) = bytes.get(pos..pos + 8) {
+            let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+            if &header[4..8] == b"acTL" {
+                let frames = bytes.get(pos + 8..pos + 12)?;
+                return Some(
+                    u32::from_be_bytes([frames[0], frames[1], frames[2], frames[3]]) as usize,
+                );
+            }
+            if &header[4..8] == b"IDAT" {
+                break;
+            }
+            pos += 12 + size;
+        }
+    }
+
+    None
 }
 
 pub fn protocols_from_animated_bytes(
-    bytes: &[u8],
-    picker: &mut Picker,
-    fps: f64,
+    bytes: Vec<u8>,
+    picker: &Picker,
+    options: AnimationOptions,
 ) -> Result<ImageProtocol> {
-    let cursor = Cursor::new(bytes);
+    if !is_animated(&bytes) {
+        let img = image::load_from_memory(&bytes)?;
+        let zoom = ZoomView::new((img.width(), img.height()), picker.clone());
+        return Ok(ImageProtocol::Single(
+            picker.new_resize_protocol(img),
+            Box::new(zoom),
+        ));
+    }
 
-    let frames_result: Result<Vec<Frame>, image::ImageError> =
-        if let Ok(decoder) = GifDecoder::new(cursor.clone()) {
-            decoder.into_frames().collect_frames()
-        } else if let Ok(decoder) = WebPDecoder::new(cursor) {
-            decoder.into_frames().collect_frames()
-        } else {
-            let img = image::load_from_memory(bytes)?;
-            return Ok(ImageProtocol::Single(picker.new_resize_protocol(img)));
+    Ok(ImageProtocol::Animated(Animation::spawn(
+        bytes.into(),
+        picker.clone(),
+        options,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn(bytes: &[u8]) -> Animation {
+        Animation::spawn(
+            Arc::from(bytes),
+            Picker::from_fontsize((8, 16)),
+            AnimationOptions::default(),
+        )
+    }
+
+    fn wait_for(animation: &mut Animation, done: impl Fn(&Animation) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done(animation) && Instant::now() < deadline {
+            animation.try_advance();
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn undecodable_animation_counts_as_played() {
+        let mut animation = spawn(b"GIF89a");
+        wait_for(&mut animation, |animation| animation.error().is_some());
+        assert!(animation.error().is_some());
+        assert!(animation.has_completed_loop());
+    }
+
+    fn gif(loop_count: Option<u16>, delays: &[u16]) -> Vec<u8> {
+        let mut bytes = b"GIF89a\x01\x00\x01\x00\x80\x00\x00".to_vec();
+        bytes.extend([0xFF, 0xFF, 0xFF, 0, 0, 0]);
+        if let Some(count) = loop_count {
+            bytes.extend(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01");
+            bytes.extend(count.to_le_bytes());
+            bytes.push(0);
+        }
+        for delay in delays {
+            bytes.extend([0x21, 0xF9, 0x04, 0x00]);
+            bytes.extend(delay.to_le_bytes());
+            bytes.extend([0x00, 0x00]);
+            bytes.extend([0x2C, 0, 0, 0, 0, 1, 0, 1, 0, 0]);
+            bytes.extend([0x02, 0x02, 0x44, 0x01, 0x00]);
+        }
+        bytes.push(0x3B);
+        bytes
+    }
+
+    fn webp(loop_count: Option<u16>, frames: usize) -> Vec<u8> {
+        let chunk = |kind: &[u8], data: &[u8]| {
+            let mut chunk = kind.to_vec();
+            chunk.extend((data.len() as u32).to_le_bytes());
+            chunk.extend(data);
+            chunk
         };
+        let mut body = b"WEBP".to_vec();
+        body.extend(chunk(b"VP8X", &[0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0]));
+        if let Some(count) = loop_count {
+            let mut anim = vec![0; 4];
+            anim.extend(count.to_le_bytes());
+            body.extend(chunk(b"ANIM", &anim));
+        }
+        for _ in 0..frames {
+            body.extend(chunk(b"ANMF", &[0; 16]));
+        }
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend((body.len() as u32).to_le_bytes());
+        bytes.extend(body);
+        bytes
+    }
 
-    let frames = frames_result?;
-    if frames.len() <= 1 {
-        let img = image::load_from_memory(bytes)?;
-        return Ok(ImageProtocol::Single(picker.new_resize_protocol(img)));
-    }
-
-    let frame_delay_ms = (1000.0 / fps).round() as u64;
-
-    let protocols: Vec<StatefulProtocol> = frames
-        .into_par_iter()
-        .map(|frame| {
-            let buf = frame.into_buffer();
-            let dyn_img = DynamicImage::ImageRgba8(buf);
-            let mut proto = picker.new_resize_protocol(dyn_img);
-            proto.resize_encode(
-                &ratatui_image::Resize::Fit(None),
-                ratatui::prelude::Rect::default(),
-            );
-            proto
-        })
-        .collect();
-
-    Ok(ImageProtocol::Animated {
-        frames: protocols,
-        current: 0,
-        last_change: Instant::now(),
-        frame_delay_ms,
-    })
+    fn apng(actl: Option<(u32, u32)>) -> Vec<u8> {
+        let chunk = |kind: &[u8], data: &[u8]| {
+            let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+            chunk.extend(kind);
+            chunk.extend(data);
+            chunk.extend([0; 4]);
+            chunk
+        };
+        let mut bytes = PNG_SIGNATURE.to_vec();
+        bytes.extend(chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 6, 0, 0, 0]));
+        if let Some((frames, plays)) = actl {
+            let mut data = frames.to_be_bytes().to_vec();
+            data.extend(plays.to_be_bytes());
+            bytes.extend(chunk(b"acTL", &data));
+        }
+        bytes.extend(chunk(b"IDAT", &[]));
+        bytes.extend(chunk(b"IEND", &[]));
+        bytes
+    }
+
+    #[test]
+    fn reads_gif_loops_and_frames() {
+        assert_eq!(loop_count(&gif(Some(0), &[10, 10])), LoopCount::Infinite);
+        assert_eq!(loop_count(&gif(Some(2), &[10, 10])), LoopCount::Finite(3));
+        assert_eq!(loop_count(&gif(None, &[10, 10])), LoopCount::Finite(1));
+        assert_eq!(count_frames(&gif(None, &[10, 10, 10])), Some(3));
+        assert_eq!(count_frames(&gif(None, &[10])[..20]), None);
+    }
+
+    #[test]
+    fn reads_webp_loops_and_frames() {
+        assert_eq!(loop_count(&webp(Some(0), 2)), LoopCount::Infinite);
+        assert_eq!(loop_count(&webp(Some(3), 2)), LoopCount::Finite(3));
+        assert_eq!(loop_count(&webp(None, 2)), LoopCount::Infinite);
+        assert_eq!(count_frames(&webp(Some(0), 4)), Some(4));
+        assert_eq!(count_frames(&webp(Some(0), 0)), None);
+    }
+
+    #[test]
+    fn reads_apng_loops_and_frames() {
+        assert_eq!(loop_count(&apng(Some((2, 0)))), LoopCount::Infinite);
+        assert_eq!(loop_count(&apng(Some((2, 5)))), LoopCount::Finite(5));
+        assert_eq!(loop_count(&apng(None)), LoopCount::Infinite);
+        assert_eq!(count_frames(&apng(Some((7, 0)))), Some(7));
+        assert_eq!(count_frames(&apng(None)), None);
+    }
+
+    #[test]
+    fn clamps_very_short_frame_delays() {
+        let frame = |ms| {
+            Frame::from_parts(
+                image::RgbaImage::new(1, 1),
+                0,
+                0,
+                image::Delay::from_numer_denom_ms(ms, 1),
+            )
+        };
+        assert_eq!(frame_delay(&frame(0)), CLAMPED_FRAME_DELAY);
+        assert_eq!(frame_delay(&frame(10)), CLAMPED_FRAME_DELAY);
+        assert_eq!(frame_delay(&frame(20)), Duration::from_millis(20));
+        assert_eq!(frame_delay(&frame(50)), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn single_frame_gifs_are_still_images() {
+        assert!(!is_animated(&gif(None, &[0])));
+        assert!(is_animated(&gif(Some(0), &[10, 10])));
+        let decoder = GifDecoder::new(Cursor::new(gif(Some(0), &[10, 10]))).unwrap();
+        assert_eq!(decoder.into_frames().count(), 2);
+
+        let picker = Picker::from_fontsize((8, 16));
+        let protocol =
+            protocols_from_animated_bytes(gif(None, &[0]), &picker, AnimationOptions::default())
+                .unwrap();
+        assert!(matches!(protocol, ImageProtocol::Single(..)));
+    }
+
+    fn spawn_with(bytes: Vec<u8>, options: AnimationOptions) -> Animation {
+        Animation::spawn(bytes.into(), Picker::from_fontsize((8, 16)), options)
+    }
+
+    fn frame(animation: &Animation) -> Option<usize> {
+        animation.status().map(|status| status.frame)
+    }
+
+    #[test]
+    fn fits_frames_to_the_memory_cap() {
+        assert_eq!(fit_to_memory(100, 50, usize::MAX), (100, 50));
+        assert_eq!(fit_to_memory(0, 0, 0), (0, 0));
+
+        let frames = MIN_WINDOW_FRAMES + LOOKAHEAD_FRAMES;
+        let cap = 50 * 25 * 4 * frames;
+        assert_eq!(fit_to_memory(100, 50, cap), (50, 25));
+        let (width, height) = fit_to_memory(4000, 3000, cap);
+        assert!(width as usize * height as usize * 4 * frames <= cap);
+        assert_eq!(fit_to_memory(10, 10, 1), (1, 1));
+    }
+
+    #[test]
+    fn next_index_wraps_at_the_frame_count() {
+        let mut animation = spawn_with(gif(Some(0), &[10, 10, 10]), AnimationOptions::default());
+        assert_eq!(animation.next_index(0), Some(1));
+        assert_eq!(animation.next_index(2), Some(0));
+        animation.frame_count = Some(1);
+        assert_eq!(animation.next_index(0), None);
+        animation.frame_count = None;
+        assert_eq!(animation.next_index(7), Some(8));
+    }
+
+    #[test]
+    fn stepping_and_seeking_wrap_around_both_ends() {
+        let mut animation = spawn_with(gif(Some(0), &[10, 10, 10]), AnimationOptions::default());
+        wait_for(&mut animation, |animation| frame(animation).is_some());
+        assert_eq!(frame(&animation), Some(0));
+
+        animation.step(-1);
+        wait_for(&mut animation, |animation| animation.current == Some(2));
+        assert_eq!(frame(&animation), Some(2));
+
+        animation.step(2);
+        wait_for(&mut animation, |animation| animation.current == Some(1));
+        assert_eq!(frame(&animation), Some(1));
+
+        animation.seek(3);
+        wait_for(&mut animation, |animation| animation.pending_seek.is_none());
+        assert_eq!(animation.current, Some(0));
+    }
+
+    #[test]
+    fn animations_share_one_memory_budget() {
+        let options = AnimationOptions::new(1024);
+        let mut first = spawn_with(gif(Some(0), &[10, 10, 10]), options.clone());
+        let mut second = spawn_with(gif(Some(0), &[10, 10]), options.clone());
+        wait_for(&mut first, |animation| animation.decoder_stopped);
+        wait_for(&mut second, |animation| animation.decoder_stopped);
+
+        let in_use = || options.in_use.load(Ordering::Relaxed);
+        assert_eq!(first.window_bytes, 3 * 4);
+        assert_eq!(in_use(), first.window_bytes + second.window_bytes);
+        drop(first);
+        assert_eq!(in_use(), second.window_bytes);
+        drop(second);
+        assert_eq!(in_use(), 0);
+    }
+
+    #[test]
+    fn evicts_against_frames_held_by_other_animations() {
+        let options = AnimationOptions::new(4 * (LOOKAHEAD_FRAMES + 4));
+        options.in_use.fetch_add(4 * 4, Ordering::Relaxed);
+        let mut animation = spawn_with(gif(Some(0), &[10; 6]), options.clone());
+        wait_for(&mut animation, |animation| animation.window.len() >= 2);
+        for _ in 0..20 {
+            animation.try_advance();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(animation.window.len(), 2);
+        options.in_use.fetch_sub(4 * 4, Ordering::Relaxed);
+    }
 }
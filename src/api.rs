@@ -1,168 +1,527 @@
 use {
     crate::{
         app::DownloadProgress,
-        models::{E6Post, E6PostResponse, E6PostsResponse},
+        cache::ImageCache,
+        media::{self, ImageVariant},
+        metadata::{self, MetadataMode, sidecar::SidecarFormat, with_suffix},
+        models::{E6Pool, E6Post, E6PostResponse, E6PostsResponse},
+        settings::Settings,
+        template::{FilenameTemplate, PoolContext},
     },
     color_eyre::eyre::{self, Result},
     futures::StreamExt,
+    reqwest::{
+        StatusCode,
+        header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RANGE},
+    },
+    serde::de::DeserializeOwned,
     std::{
-        fs::{self, File},
-        io::Write,
+        collections::HashMap,
+        fs::{self, File, OpenOptions},
+        io::{Read, Write},
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
     },
 };
 
 pub const USER_AGENT: &str = "E6TU1/1.0 (by bearodactyl on e621)";
 pub const BASE_URL: &str = "https://e621.net";
+pub const DEFAULT_DOWNLOAD_DIR: &str = "downloads";
+pub const MAX_PAGE_LIMIT: usize = 320;
+pub const DEFAULT_PAGE_LIMIT: usize = 50;
+const MAX_DOWNLOAD_ATTEMPTS: u32 = 3;
+pub const API_CACHE_TTL: Duration = Duration::from_secs(300);
+const API_CACHE_ENTRIES: usize = 256;
+
+/// `page=b<id>` only works for id-descending results, so `order:` queries use numbered pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageCursor {
+    BeforeId(i64),
+    Number(usize),
+}
+
+impl PageCursor {
+    pub fn next(tags: &str, current: Option<PageCursor>, posts: &[E6Post]) -> Option<Self> {
+        if is_ordered(tags) {
+            let number = match current {
+                Some(PageCursor::Number(number)) => number,
+                _ => 1,
+            };
+            Some(PageCursor::Number(number + 1))
+        } else {
+            posts
+                .iter()
+                .map(|post| post.id)
+                .min()
+                .map(PageCursor::BeforeId)
+        }
+    }
+}
+
+fn is_ordered(tags: &str) -> bool {
+    tags.split_whitespace()
+        .any(|tag| tag.to_ascii_lowercase().starts_with("order:"))
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    pub dir: PathBuf,
+    pub filename_template: FilenameTemplate,
+    pub metadata_mode: MetadataMode,
+    pub sidecar_formats: Vec<SidecarFormat>,
+    pub embed_metadata: bool,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from(DEFAULT_DOWNLOAD_DIR),
+            filename_template: FilenameTemplate::default(),
+            metadata_mode: MetadataMode::default(),
+            sidecar_formats: vec![SidecarFormat::Native],
+            embed_metadata: false,
+        }
+    }
+}
 
+#[derive(Debug)]
+pub struct SavedDownload {
+    pub path: PathBuf,
+    pub embed_error: Option<String>,
+}
+
+struct CachedResponse {
+    body: Vec<u8>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: Option<Instant>,
+}
+
+impl CachedResponse {
+    fn is_fresh(&self) -> bool {
+        self.fetched_at
+            .is_some_and(|fetched_at| fetched_at.elapsed() < API_CACHE_TTL)
+    }
+}
+
+#[derive(Clone)]
 pub struct E621Client {
     client: reqwest::Client,
+    download_options: DownloadOptions,
+    page_limit: usize,
+    cache: Arc<ImageCache>,
+    api_cache: Arc<Mutex<HashMap<String, CachedResponse>>>,
 }
 
 impl E621Client {
-    pub fn new() -> Self {
+    pub fn new(settings: &Settings, cache: Arc<ImageCache>) -> Self {
         Self {
             client: reqwest::Client::builder()
                 .user_agent(USER_AGENT)
                 .build()
                 .expect("Failed to create HTTP client"),
+            download_options: settings.download.clone(),
+            page_limit: settings.page_limit,
+            cache,
+            api_cache: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    pub async fn search_posts(&self, tags: &str) -> Result<Vec<E6Post>> {
-        let url = format!(
-            "{}/posts.json?tags={}&limit=50",
-            BASE_URL,
-            urlencoding::encode(tags)
-        );
+    pub fn page_limit(&self) -> usize {
+        self.page_limit
+    }
 
-        let response = self.client.get(&url).send().await?;
+    pub fn expire_api_cache(&self) {
+        for entry in self.api_cache.lock().unwrap().values_mut() {
+            entry.fetched_at = None;
+        }
+    }
 
+    async fn get_json<T: DeserializeOwned>(&self, url: &str, action: &str) -> Result<T> {
+        let response = self.client.get(url).send().await?;
         if !response.status().is_success() {
-            return Err(eyre::Error::msg(format!(
-                "Failed to search posts: HTTP {}",
-                response.status()
-            )));
+            eyre::bail!("Failed to {}: HTTP {}", action, response.status());
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn get_json_cached<T: DeserializeOwned>(&self, url: &str, action: &str) -> Result<T> {
+        let (etag, last_modified) = {
+            let cache = self.api_cache.lock().unwrap();
+            match cache.get(url) {
+                Some(entry) if entry.is_fresh() => return Ok(serde_json::from_slice(&entry.body)?),
+                Some(entry) => (entry.etag.clone(), entry.last_modified.clone()),
+                None => (None, None),
+            }
+        };
+
+        let mut request = self.client.get(url);
+        if let Some(ref etag) = etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(ref last_modified) = last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let mut response = request.send().await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            {
+                let mut cache = self.api_cache.lock().unwrap();
+                if let Some(entry) = cache.get_mut(url) {
+                    entry.fetched_at = Some(Instant::now());
+                    return Ok(serde_json::from_slice(&entry.body)?);
+                }
+            }
+            // The entry was evicted while the request was in flight.
+            response = self.client.get(url).send().await?;
+        }
+
+        if !response.status().is_success() {
+            eyre::bail!("Failed to {}: HTTP {}", action, response.status());
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = response.bytes().await?.to_vec();
+        let value = serde_json::from_slice(&body)?;
+
+        let mut cache = self.api_cache.lock().unwrap();
+        if cache.len() >= API_CACHE_ENTRIES
+            && !cache.contains_key(url)
+            && let Some(oldest) = cache
+                .iter()
+                .min_by_key(|(_, entry)| entry.fetched_at)
+                .map(|(url, _)| url.clone())
+        {
+            cache.remove(&oldest);
         }
+        cache.insert(
+            url.to_string(),
+            CachedResponse {
+                body,
+                etag,
+                last_modified,
+                fetched_at: Some(Instant::now()),
+            },
+        );
+
+        Ok(value)
+    }
 
-        let posts_response: E6PostsResponse = response.json().await?;
+    pub async fn search_posts(&self, tags: &str) -> Result<Vec<E6Post>> {
+        let url = search_url(tags, self.page_limit, None);
+        let posts_response: E6PostsResponse = self.get_json_cached(&url, "search posts").await?;
         Ok(posts_response.posts)
     }
 
-    pub async fn fetch_post(&self, post_id: &str) -> Result<E6Post> {
-        let url = format!("{}/posts/{}.json", BASE_URL, post_id);
+    /// Further pages and bulk listings, which bypass the response cache.
+    pub async fn search_posts_page(
+        &self,
+        tags: &str,
+        limit: usize,
+        page: Option<PageCursor>,
+    ) -> Result<Vec<E6Post>> {
+        let url = search_url(tags, limit, page);
+        let posts_response: E6PostsResponse = self.get_json(&url, "search posts").await?;
+        Ok(posts_response.posts)
+    }
+
+    pub async fn fetch_pool(&self, pool_id: &str) -> Result<E6Pool> {
+        let url = format!("{}/pools/{}.json", BASE_URL, pool_id);
 
         let response = self.client.get(&url).send().await?;
 
         if !response.status().is_success() {
             return Err(eyre::Error::msg(format!(
-                "Failed to fetch post: HTTP {}",
+                "Failed to fetch pool: HTTP {}",
                 response.status()
             )));
         }
 
-        let post_response: E6PostResponse = response.json().await?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn fetch_pool_posts(&self, pool: &E6Pool) -> Result<Vec<E6Post>> {
+        let tags = format!("pool:{}", pool.id);
+        let mut posts = Vec::new();
+        let mut cursor = None;
+
+        loop {
+            let page = self
+                .search_posts_page(&tags, MAX_PAGE_LIMIT, cursor)
+                .await?;
+            let page_len = page.len();
+            cursor = PageCursor::next(&tags, cursor, &page);
+            posts.extend(page);
+
+            if page_len < MAX_PAGE_LIMIT {
+                break;
+            }
+        }
+
+        sort_by_pool_order(&mut posts, pool);
+        Ok(posts)
+    }
+
+    pub async fn fetch_post(&self, post_id: &str) -> Result<E6Post> {
+        let url = format!("{}/posts/{}.json", BASE_URL, post_id);
+
+        let post_response: E6PostResponse = self.get_json_cached(&url, "fetch post").await?;
         Ok(post_response.post)
     }
 
-    pub async fn download_image_bytes(&self, url: &str) -> Result<Vec<u8>> {
+    pub async fn fetch_image(&self, post: &E6Post, variant: ImageVariant) -> Result<Vec<u8>> {
+        if let Some(bytes) = self.cache.get(&post.file.md5, variant) {
+            if is_intact(post, variant, &bytes) {
+                return Ok(bytes);
+            }
+            self.cache.remove(&post.file.md5, variant);
+        }
+
+        let url = media::variant_url(post, variant)
+            .ok_or_else(|| eyre::eyre!("Post has no {} image", variant.label()))?;
         let response = self.client.get(url).send().await?;
-        let bytes = response.bytes().await?;
+        if !response.status().is_success() {
+            eyre::bail!("Image request failed with status: {}", response.status());
+        }
+        let bytes = response.bytes().await?.to_vec();
 
-        Ok(bytes.to_vec())
+        if is_intact(post, variant, &bytes) {
+            let _ = self.cache.put(&post.file.md5, variant, &bytes);
+        }
+        Ok(bytes)
     }
 
     pub async fn download_post_to_file(
         &self,
         post: &E6Post,
-        progress: &mut Option<DownloadProgress>,
-    ) -> Result<()> {
+        pool: Option<&E6Pool>,
+        progress: &mut (dyn FnMut(&DownloadProgress) + Send),
+    ) -> Result<SavedDownload> {
         let image_url = post
             .file
             .url
             .as_ref()
             .ok_or_else(|| eyre::Error::msg("Post has no image URL"))?;
 
-        fs::create_dir_all("downloads")?;
+        let pool_context = self.pool_context(post, pool).await;
+        let path = self.download_options.dir.join(
+            self.download_options
+                .filename_template
+                .render(post, pool_context.as_ref()),
+        );
 
-        let tags: Vec<String> = post
-            .tags
-            .general
-            .iter()
-            .chain(post.tags.artist.iter())
-            .chain(post.tags.character.iter())
-            .take(3)
-            .map(|s| s.replace(['/', '\\'], "_"))
-            .collect();
-
-        let tag_string = if tags.is_empty() {
-            "untagged".to_string()
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let part_path = with_suffix(&path, ".part");
+        let mut last_error = None;
+
+        // A corrupt cached original is dropped and fetched again.
+        if let Some(cached) = self.cache.lookup(&post.file.md5, ImageVariant::Original)
+            && fs::copy(cached, &part_path).is_ok()
+        {
+            if md5_matches(post, &file_md5(&part_path)?) {
+                return self.finish_download(post, &part_path, path);
+            }
+            fs::remove_file(&part_path)?;
+            self.cache.remove(&post.file.md5, ImageVariant::Original);
+        }
+
+        for attempt in 1..=MAX_DOWNLOAD_ATTEMPTS {
+            if let Err(e) = self
+                .download_to_part(image_url, &part_path, &path, progress)
+                .await
+            {
+                last_error = Some(e);
+                continue;
+            }
+
+            let actual_md5 = file_md5(&part_path)?;
+            if md5_matches(post, &actual_md5) {
+                let _ = self
+                    .cache
+                    .put_file(&post.file.md5, ImageVariant::Original, &part_path);
+                return self.finish_download(post, &part_path, path);
+            }
+
+            fs::remove_file(&part_path)?;
+            last_error = Some(eyre::eyre!(
+                "MD5 mismatch for {} (attempt {}/{}): expected {}, got {}",
+                path.display(),
+                attempt,
+                MAX_DOWNLOAD_ATTEMPTS,
+                post.file.md5,
+                actual_md5
+            ));
+        }
+
+        Err(last_error.unwrap_or_else(|| eyre::Error::msg("Download failed")))
+    }
+
+    fn finish_download(
+        &self,
+        post: &E6Post,
+        part_path: &Path,
+        path: PathBuf,
+    ) -> Result<SavedDownload> {
+        fs::rename(part_path, &path)?;
+        let embed_error = if self.download_options.embed_metadata {
+            metadata::xmp::embed(post, &path)
+                .err()
+                .map(|e| e.to_string())
         } else {
-            tags.join("_")
+            None
         };
+        metadata::save_metadata(
+            post,
+            &path,
+            self.download_options.metadata_mode,
+            &self.download_options.sidecar_formats,
+        )?;
+        Ok(SavedDownload { path, embed_error })
+    }
 
-        let filename = format!(
-            "downloads/{} - {} - {}.{}",
-            post.id, tag_string, post.file.md5, post.file.ext
-        );
-
-        let response = self.client.get(image_url).send().await?;
+    async fn pool_context(&self, post: &E6Post, pool: Option<&E6Pool>) -> Option<PoolContext> {
+        if !self.download_options.filename_template.needs_pool() {
+            return None;
+        }
 
-        if !response.status().is_success() {
-            eyre::bail!("Download failed with status: {}", response.status());
+        if let Some(context) = pool.and_then(|pool| PoolContext::for_post(pool, post)) {
+            return Some(context);
         }
 
-        let total_size = response.content_length().unwrap_or(0);
+        let pool_id = post.pools.first()?;
+        let pool = self.fetch_pool(&pool_id.to_string()).await.ok()?;
+        PoolContext::for_post(&pool, post)
+    }
+
+    async fn download_to_part(
+        &self,
+        url: &str,
+        part_path: &Path,
+        path: &Path,
+        progress: &mut (dyn FnMut(&DownloadProgress) + Send),
+    ) -> Result<()> {
+        let resume_from = fs::metadata(part_path).map(|m| m.len()).unwrap_or(0);
 
-        if let Some(p) = progress {
-            p.total_bytes = total_size;
-            p.downloaded_bytes = 0;
-            p.message = format!(
-                "Downloading {} ({:.2} MB)",
-                filename,
-                total_size as f64 / 1_048_576.0
-            );
+        let mut request = self.client.get(url);
+        if resume_from > 0 {
+            request = request.header(RANGE, format!("bytes={}-", resume_from));
         }
 
-        let mut file = File::create(&filename)?;
+        let response = request.send().await?;
+
+        let (mut file, mut downloaded) = match response.status() {
+            StatusCode::PARTIAL_CONTENT => (
+                OpenOptions::new().append(true).open(part_path)?,
+                resume_from,
+            ),
+            StatusCode::RANGE_NOT_SATISFIABLE if resume_from > 0 => return Ok(()),
+            status if status.is_success() => (File::create(part_path)?, 0),
+            status => eyre::bail!("Download failed with status: {}", status),
+        };
+
+        let total_size = response
+            .content_length()
+            .map(|len| len + downloaded)
+            .unwrap_or(0);
+
+        let mut status = DownloadProgress {
+            total_bytes: total_size,
+            downloaded_bytes: downloaded,
+            message: if downloaded > 0 {
+                format!(
+                    "Resuming {} at {:.2} MB ({:.2} MB)",
+                    path.display(),
+                    downloaded as f64 / 1_048_576.0,
+                    total_size as f64 / 1_048_576.0
+                )
+            } else {
+                format!(
+                    "Downloading {} ({:.2} MB)",
+                    path.display(),
+                    total_size as f64 / 1_048_576.0
+                )
+            },
+        };
+        progress(&status);
+
         let mut stream = response.bytes_stream();
-        let mut downloaded: u64 = 0;
 
         while let Some(chunk) = stream.next().await {
             let chunk = chunk?;
             file.write_all(&chunk)?;
             downloaded += chunk.len() as u64;
 
-            if let Some(p) = progress {
-                p.downloaded_bytes = downloaded;
-            }
+            status.downloaded_bytes = downloaded;
+            progress(&status);
         }
 
         file.flush()?;
 
-        self.save_metadata(post, &filename)?;
-
         Ok(())
     }
+}
+
+fn search_url(tags: &str, limit: usize, page: Option<PageCursor>) -> String {
+    let mut url = format!(
+        "{}/posts.json?tags={}&limit={}",
+        BASE_URL,
+        urlencoding::encode(tags),
+        limit
+    );
+
+    match page {
+        Some(PageCursor::BeforeId(id)) => url.push_str(&format!("&page=b{}", id)),
+        Some(PageCursor::Number(number)) => url.push_str(&format!("&page={}", number)),
+        None => {}
+    }
+    url
+}
 
-    fn save_metadata(&self, post: &E6Post, base_filename: &str) -> Result<()> {
-        let metadata_json = serde_json::to_string_pretty(post)?;
+fn md5_matches(post: &E6Post, actual: &str) -> bool {
+    post.file.md5.is_empty() || actual.eq_ignore_ascii_case(&post.file.md5)
+}
 
-        #[cfg(target_os = "windows")]
-        {
-            let ads_path = format!("{}:metadata", base_filename);
-            let mut ads_file = File::create(&ads_path)?;
-            ads_file.write_all(metadata_json.as_bytes())?;
-            ads_file.flush()?;
-        }
+/// Only originals have a published md5; other variants just have to decode.
+fn is_intact(post: &E6Post, variant: ImageVariant, bytes: &[u8]) -> bool {
+    match variant {
+        ImageVariant::Original => md5_matches(post, &format!("{:x}", md5::compute(bytes))),
+        ImageVariant::Preview | ImageVariant::Sample => image::guess_format(bytes).is_ok(),
+    }
+}
 
-        #[cfg(not(target_os = "windows"))]
-        {
-            let json_path = format!("{}.json", base_filename);
-            let mut json_file = File::create(&json_path)?;
-            json_file.write_all(metadata_json.as_bytes())?;
-            json_file.flush()?;
-        }
+fn file_md5(path: impl AsRef<Path>) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut context = md5::Context::new();
+    let mut buf = vec![0u8; 64 * 1024];
 
-        Ok(())
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        context.consume(&buf[..read]);
     }
+
+    Ok(format!("{:x}", context.finalize()))
+}
+
+pub fn sort_by_pool_order(posts: &mut [E6Post], pool: &E6Pool) {
+    posts.sort_by_key(|post| {
+        pool.post_ids
+            .iter()
+            .position(|&id| id == post.id)
+            .unwrap_or(usize::MAX)
+    });
 }
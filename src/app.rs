@@ -1,16 +1,43 @@
 use {
     crate::{
-        api::{BASE_URL, E621Client},
+        anim::{self, AnimationOptions, ImageProtocol},
+        api::{self, BASE_URL, E621Client, MAX_PAGE_LIMIT, PageCursor, SavedDownload},
+        bulk::{BulkDownload, BulkSource},
+        cache::ImageCache,
         event::AppEvent,
-        models::E6Post,
-        widgets::post_popup::E6PostPopupState,
+        index::DownloadIndex,
+        media::{self, ImageQuality, ImageVariant, MediaKind},
+        models::{E6Pool, E6Post},
+        preload::Preloader,
+        preview::SelectionPreview,
+        settings::{Settings, UiSettings},
+        slideshow::{Slideshow, SlideshowOptions},
+        tasks::{TaskKind, TaskMessage, Tasks},
+        thumbnails::Thumbnails,
+        video::{self, PlayerOptions},
+        widgets::{
+            post_popup::{E6PostPopupState, PopupLink},
+            thumbnail_grid::ThumbnailGridState,
+        },
+        zoom::ZoomLevel,
     },
     color_eyre::eyre::Result,
-    crossterm::event::KeyCode,
-    ratatui::widgets::ListState,
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
+    image::DynamicImage,
+    ratatui::{
+        layout::{Position, Rect},
+        widgets::ListState,
+    },
     ratatui_image::picker::Picker,
+    std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    },
 };
 
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+const WHEEL_SCROLL_LINES: i32 = 3;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AppState {
     Input,
@@ -21,10 +48,17 @@ pub enum AppState {
     Error,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultsView {
+    List,
+    Grid,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputMode {
     TagSearch,
     PostId,
+    PoolId,
 }
 
 #[derive(Clone, Debug)]
@@ -35,14 +69,6 @@ pub struct DownloadProgress {
 }
 
 impl DownloadProgress {
-    pub fn new(message: String) -> Self {
-        Self {
-            total_bytes: 0,
-            downloaded_bytes: 0,
-            message,
-        }
-    }
-
     pub fn ratio(&self) -> f64 {
         if self.total_bytes == 0 {
             0.0
@@ -59,25 +85,48 @@ pub struct App {
     pub tag_cursor_position: usize,
     pub id_input: String,
     pub id_cursor_position: usize,
+    pub pool_input: String,
+    pub pool_cursor_position: usize,
     pub post: Option<E6Post>,
     pub search_results: Vec<E6Post>,
+    pub current_pool: Option<E6Pool>,
     pub list_state: ListState,
+    pub list_area: Rect,
+    last_click: Option<(Instant, usize)>,
+    pub results_view: ResultsView,
+    pub grid_state: ThumbnailGridState,
+    pub selection_preview: SelectionPreview,
+    pub preloader: Preloader,
+    pub slideshow: Option<Slideshow>,
+    pub slideshow_options: SlideshowOptions,
+    results_exhausted: bool,
+    next_page: Option<PageCursor>,
     pub popup_state: E6PostPopupState,
     pub picker: Picker,
+    pub animation_options: AnimationOptions,
+    pub image_quality: ImageQuality,
+    pub player_options: PlayerOptions,
+    pub ui: UiSettings,
     pub error_message: Option<String>,
     pub download_progress: Option<DownloadProgress>,
-
-    pending_search: bool,
-    pending_fetch: bool,
-    pending_load_image: bool,
-    pending_download: bool,
-    pending_open_browser: bool,
+    downloading_post: Option<i64>,
+    pub bulk_download: Option<BulkDownload>,
+    pub notice: Option<String>,
+    pub download_index: DownloadIndex,
+    pub tasks: Tasks,
+    pub ticks: u64,
+    terminal_size: (u16, u16),
 
     client: E621Client,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(settings: &Settings, cache: Arc<ImageCache>) -> Self {
+        let picker = Picker::from_query_stdio().unwrap();
+        let download_index = DownloadIndex::load(&settings.download.dir);
+        let client = E621Client::new(settings, cache);
+        let animation_options = settings.animation.clone();
+
         Self {
             state: AppState::Input,
             input_mode: InputMode::TagSearch,
@@ -85,110 +134,344 @@ impl App {
             tag_cursor_position: 0,
             id_input: String::new(),
             id_cursor_position: 0,
+            pool_input: String::new(),
+            pool_cursor_position: 0,
             post: None,
             search_results: Vec::new(),
+            current_pool: None,
             list_state: ListState::default(),
+            list_area: Rect::default(),
+            last_click: None,
+            results_view: ResultsView::List,
+            grid_state: ThumbnailGridState::new(Thumbnails::new(client.clone(), picker.clone())),
+            selection_preview: SelectionPreview::new(
+                client.clone(),
+                picker.clone(),
+                animation_options.clone(),
+            ),
+            preloader: Preloader::new(client.clone(), picker.clone(), animation_options.clone()),
+            slideshow: None,
+            slideshow_options: settings.slideshow,
+            results_exhausted: false,
+            next_page: None,
             popup_state: E6PostPopupState::new(),
-            picker: Picker::from_query_stdio().unwrap(),
+            picker,
+            animation_options,
+            image_quality: settings.image_quality,
+            player_options: settings.player.clone(),
+            ui: settings.ui,
             error_message: None,
             download_progress: None,
-            pending_search: false,
-            pending_fetch: false,
-            pending_load_image: false,
-            pending_download: false,
-            pending_open_browser: false,
-            client: E621Client::new(),
+            downloading_post: None,
+            bulk_download: None,
+            notice: None,
+            download_index,
+            tasks: Tasks::new(),
+            ticks: 0,
+            terminal_size: crossterm::terminal::size().unwrap_or((80, 24)),
+            client,
         }
     }
 
-    pub async fn handle_event(&mut self, event: AppEvent) -> Result<bool> {
+    pub fn handle_event(&mut self, event: AppEvent) -> Result<bool> {
         match event {
-            AppEvent::Key(key) => match self.state {
-                AppState::Input => self.handle_input_key(key),
-                AppState::Loading => {}
-                AppState::SearchResults => self.handle_search_results_key(key),
-                AppState::Viewing => self.handle_viewing_key(key),
-                AppState::FullImageView => self.handle_full_image_key(key),
-                AppState::Error => {
-                    self.state = AppState::Input;
-                    self.error_message = None;
+            AppEvent::Key(key) => {
+                if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                    return Ok(false);
                 }
-            },
+
+                self.notice = None;
+                if self.state == AppState::Input {
+                    return Ok(self.handle_input_key(key));
+                }
+                // Bindings are bare keys outside text entry, so Ctrl/Alt chords are ignored.
+                if key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                {
+                    return Ok(true);
+                }
+
+                match self.state {
+                    AppState::Input => {}
+                    AppState::Loading => self.handle_loading_key(key.code),
+                    AppState::SearchResults => self.handle_search_results_key(key.code),
+                    AppState::Viewing => self.handle_viewing_key(key.code),
+                    AppState::FullImageView => self.handle_full_image_key(key.code),
+                    AppState::Error => {
+                        self.state = AppState::Input;
+                        self.error_message = None;
+                    }
+                }
+            }
+            AppEvent::Paste(text) => {
+                if self.state == AppState::Input {
+                    self.paste(&text);
+                }
+            }
+            AppEvent::Resize(columns, rows) => {
+                self.terminal_size = (columns, rows);
+                // A bigger window may call for a bigger rendition; smaller ones are kept.
+                if matches!(self.state, AppState::Viewing | AppState::FullImageView) {
+                    self.load_image();
+                }
+            }
+            AppEvent::Mouse(mouse) => self.handle_mouse(mouse),
             AppEvent::Tick => {
+                self.ticks = self.ticks.wrapping_add(1);
                 if let Some(ref mut protocol) = self.popup_state.image_protocol {
                     protocol.try_advance();
                 }
             }
+            AppEvent::Task(message) => self.handle_task_message(*message),
         }
         Ok(true)
     }
 
-    pub async fn process_pending_operations(&mut self) -> Result<()> {
-        if self.pending_search {
-            self.pending_search = false;
-            self.download_progress = Some(DownloadProgress::new("Searching posts...".to_string()));
-            if let Err(e) = self.search_posts().await {
+    pub fn update(&mut self) {
+        if self.slideshow.is_some() {
+            if self.state == AppState::FullImageView {
+                self.step_slideshow();
+            } else {
+                self.slideshow = None;
+            }
+        }
+
+        if matches!(self.state, AppState::Viewing | AppState::FullImageView) {
+            self.preload_neighbours();
+        }
+
+        if self.state == AppState::SearchResults {
+            match self.results_view {
+                ResultsView::List => self.update_selection_preview(),
+                ResultsView::Grid => self.update_thumbnails(),
+            }
+        }
+
+        if self.bulk_download.is_some() {
+            self.step_bulk_download();
+        }
+    }
+
+    fn handle_task_message(&mut self, message: TaskMessage) {
+        match message {
+            TaskMessage::Searched(Ok(posts)) => self.show_search_results(posts),
+            TaskMessage::Searched(Err(e)) => {
                 self.error_message = Some(format!("Failed to search posts: {}", e));
                 self.state = AppState::Error;
             }
-            self.download_progress = None;
+            TaskMessage::PostFetched(Ok(post)) => {
+                self.post = Some(post);
+                self.search_results.clear();
+                self.current_pool = None;
+                self.state = AppState::Viewing;
+                self.popup_state = E6PostPopupState::new();
+                self.load_image();
+            }
+            TaskMessage::PostFetched(Err(e)) => {
+                self.error_message = Some(format!("Failed to fetch post: {}", e));
+                self.state = AppState::Error;
+            }
+            TaskMessage::PoolFetched(Ok((pool, posts))) => self.show_pool_results(pool, posts),
+            TaskMessage::PoolFetched(Err(e)) => {
+                self.error_message = Some(format!("Failed to fetch pool: {}", e));
+                self.state = AppState::Error;
+            }
+            TaskMessage::PostRefreshed(Ok(post)) => {
+                if let Some(result) = self
+                    .search_results
+                    .iter_mut()
+                    .find(|result| result.id == post.id)
+                {
+                    *result = post.clone();
+                }
+                if self
+                    .post
+                    .as_ref()
+                    .is_some_and(|current| current.id == post.id)
+                {
+                    self.post = Some(post);
+                    self.notice = Some("Post refreshed".to_string());
+                }
+            }
+            TaskMessage::PostRefreshed(Err(e)) => {
+                self.notice = Some(format!("Failed to refresh post: {}", e));
+            }
+            TaskMessage::ImageLoaded {
+                post_id,
+                variant,
+                result,
+            } => self.show_loaded_image(post_id, variant, result),
+            TaskMessage::ZoomSourceLoaded { post_id, result } => {
+                self.show_zoom_source(post_id, result);
+            }
+            TaskMessage::PageLoaded { advance, result } => self.append_page(advance, result),
+            TaskMessage::DownloadProgress(progress) => self.download_progress = Some(progress),
+            TaskMessage::Downloaded { post, result } => {
+                self.download_progress = None;
+                let result = result.and_then(|saved| {
+                    self.download_index.insert(&post, &saved.path)?;
+                    Ok(saved)
+                });
+                self.notice = Some(match result {
+                    Ok(SavedDownload {
+                        path,
+                        embed_error: Some(e),
+                    }) => format!(
+                        "Saved to {} without embedded metadata: {}",
+                        path.display(),
+                        e
+                    ),
+                    Ok(saved) => format!("Saved to {}", saved.path.display()),
+                    Err(e) => format!("Failed to download #{}: {}", post.id, e),
+                });
+            }
+            TaskMessage::BulkPageListed(result) => self.enqueue_bulk_page(result),
+            TaskMessage::BulkPostDownloaded { post, result } => {
+                self.finish_bulk_post(post, result);
+            }
         }
+    }
 
-        if self.pending_fetch {
-            self.pending_fetch = false;
-            self.download_progress = Some(DownloadProgress::new("Fetching post...".to_string()));
-            match self.fetch_post().await {
-                Ok(_) => self.pending_load_image = true,
-                Err(e) => {
-                    self.error_message = Some(format!("Failed to fetch post: {}", e));
-                    self.state = AppState::Error;
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let position = Position::new(mouse.column, mouse.row);
+        match self.state {
+            AppState::SearchResults => self.handle_results_mouse(mouse.kind, position),
+            AppState::Viewing => self.handle_popup_mouse(mouse.kind, position),
+            _ => {}
+        }
+    }
+
+    fn handle_results_mouse(&mut self, kind: MouseEventKind, position: Position) {
+        let results_area = match self.results_view {
+            ResultsView::List => self.list_area,
+            ResultsView::Grid => self.grid_state.area,
+        };
+
+        match kind {
+            MouseEventKind::ScrollUp if results_area.contains(position) => {
+                self.handle_search_results_key(KeyCode::Up);
+            }
+            MouseEventKind::ScrollDown if results_area.contains(position) => {
+                self.handle_search_results_key(KeyCode::Down);
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(index) = self.result_at(position) else {
+                    return;
+                };
+                let double_click = self.last_click.is_some_and(|(at, clicked)| {
+                    clicked == index && at.elapsed() <= DOUBLE_CLICK_INTERVAL
+                });
+
+                self.list_state.select(Some(index));
+                if double_click {
+                    self.last_click = None;
+                    self.handle_search_results_key(KeyCode::Enter);
+                } else {
+                    self.last_click = Some((Instant::now(), index));
                 }
             }
-            self.download_progress = None;
+            _ => {}
         }
+    }
 
-        if self.pending_load_image {
-            self.pending_load_image = false;
-            self.download_progress = Some(DownloadProgress::new("Loading image...".to_string()));
-            if let Err(e) = self.load_image().await {
-                eprintln!("Failed to load image: {}", e);
+    fn result_at(&self, position: Position) -> Option<usize> {
+        let index = match self.results_view {
+            ResultsView::List => {
+                if !self.list_area.contains(position) {
+                    return None;
+                }
+                self.list_state.offset() + (position.y - self.list_area.y) as usize
             }
-            self.download_progress = None;
-        }
+            ResultsView::Grid => self.grid_state.index_at(position)?,
+        };
+        (index < self.search_results.len()).then_some(index)
+    }
 
-        if self.pending_download {
-            self.pending_download = false;
-            if let Err(e) = self.download_post().await {
-                self.error_message = Some(format!("Failed to download: {}", e));
-                self.state = AppState::Error;
+    fn handle_popup_mouse(&mut self, kind: MouseEventKind, position: Position) {
+        match kind {
+            MouseEventKind::ScrollUp if self.popup_state.info_area.contains(position) => {
+                self.popup_state.scroll(-WHEEL_SCROLL_LINES);
+            }
+            MouseEventKind::ScrollDown if self.popup_state.info_area.contains(position) => {
+                self.popup_state.scroll(WHEEL_SCROLL_LINES);
+            }
+            MouseEventKind::Down(MouseButton::Left)
+                if !self.popup_state.area.contains(position) =>
+            {
+                self.close_post();
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                match self.popup_state.link_at(position).cloned() {
+                    Some(PopupLink::Tag(tag)) => self.search_tag(tag),
+                    Some(PopupLink::Source(url)) => {
+                        if let Err(e) = open::that(&url) {
+                            self.notice = Some(format!("Failed to open {}: {}", url, e));
+                        }
+                    }
+                    None => {}
+                }
             }
+            _ => {}
         }
+    }
 
-        if self.pending_open_browser {
-            self.pending_open_browser = false;
-            if let Err(e) = self.open_in_browser() {
-                self.error_message = Some(format!("Failed to open browser: {}", e));
-                self.state = AppState::Error;
+    fn search_tag(&mut self, tag: String) {
+        self.close_post();
+        self.search_results.clear();
+        self.current_pool = None;
+        self.tag_cursor_position = tag.chars().count();
+        self.tag_input = tag;
+        self.input_mode = InputMode::TagSearch;
+        self.state = AppState::Loading;
+        self.start_search();
+    }
+
+    fn handle_loading_key(&mut self, key_code: KeyCode) {
+        if matches!(key_code, KeyCode::Char('q') | KeyCode::Esc) {
+            for kind in [TaskKind::Search, TaskKind::FetchPost, TaskKind::FetchPool] {
+                self.tasks.cancel(kind);
             }
+            self.state = AppState::Input;
         }
+    }
 
-        Ok(())
+    pub fn loading_message(&self) -> &'static str {
+        if self.tasks.is_running(TaskKind::Search) {
+            "Searching posts..."
+        } else if self.tasks.is_running(TaskKind::FetchPost) {
+            "Fetching post..."
+        } else if self.tasks.is_running(TaskKind::FetchPool) {
+            "Fetching pool..."
+        } else {
+            "Loading..."
+        }
     }
 
-    fn handle_input_key(&mut self, key_code: KeyCode) {
-        match key_code {
-            KeyCode::Char('q') => std::process::exit(0),
+    fn handle_input_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('w') if ctrl => self.delete_word(),
+            KeyCode::Char('u') if ctrl => self.clear_input(),
+            KeyCode::Char(_) if ctrl || key.modifiers.contains(KeyModifiers::ALT) => {}
+            KeyCode::Char('q') => return false,
             KeyCode::Enter => match self.input_mode {
                 InputMode::TagSearch => {
                     if !self.tag_input.is_empty() {
                         self.state = AppState::Loading;
-                        self.pending_search = true;
+                        self.start_search();
                     }
                 }
                 InputMode::PostId => {
                     if !self.id_input.is_empty() {
                         self.state = AppState::Loading;
-                        self.pending_fetch = true;
+                        self.start_fetch_post();
+                    }
+                }
+                InputMode::PoolId => {
+                    if !self.pool_input.is_empty() {
+                        self.state = AppState::Loading;
+                        self.start_fetch_pool();
                     }
                 }
             },
@@ -197,26 +480,50 @@ impl App {
             KeyCode::Left => self.move_cursor_left(),
             KeyCode::Right => self.move_cursor_right(),
             KeyCode::Tab => self.switch_input_mode(),
-            KeyCode::Esc => match self.input_mode {
-                InputMode::TagSearch => {
-                    self.tag_input.clear();
-                    self.tag_cursor_position = 0;
-                }
-                InputMode::PostId => {
-                    self.id_input.clear();
-                    self.id_cursor_position = 0;
-                }
-            },
+            KeyCode::BackTab => self.switch_input_mode_back(),
+            KeyCode::Esc => self.clear_input(),
             _ => {}
         }
+        true
     }
 
     fn handle_search_results_key(&mut self, key_code: KeyCode) {
         match key_code {
+            KeyCode::Esc if self.bulk_download.is_some() => self.cancel_bulk_download(),
             KeyCode::Char('q') | KeyCode::Esc => {
+                for kind in [TaskKind::Search, TaskKind::FetchPool, TaskKind::NextPage] {
+                    self.tasks.cancel(kind);
+                }
                 self.state = AppState::Input;
                 self.search_results.clear();
+                self.current_pool = None;
                 self.list_state.select(None);
+                self.grid_state.thumbnails.clear();
+                self.selection_preview.clear();
+            }
+            KeyCode::Char('D') => self.start_bulk_download(),
+            KeyCode::Char('r') => {
+                self.client.expire_api_cache();
+                self.notice = Some("Refreshing results...".to_string());
+                match self.current_pool {
+                    Some(ref pool) => {
+                        self.pool_input = pool.id.to_string();
+                        self.start_fetch_pool();
+                    }
+                    None => self.start_search(),
+                }
+            }
+            KeyCode::Char('s') => self.start_slideshow(),
+            KeyCode::Char('g') => {
+                self.results_view = match self.results_view {
+                    ResultsView::List => ResultsView::Grid,
+                    ResultsView::Grid => ResultsView::List,
+                };
+            }
+            KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right
+                if self.results_view == ResultsView::Grid =>
+            {
+                self.move_grid_selection(key_code);
             }
             KeyCode::Up => {
                 let i = match self.list_state.selected() {
@@ -251,50 +558,449 @@ impl App {
                     self.post = Some(post);
                     self.state = AppState::Viewing;
                     self.popup_state = E6PostPopupState::new();
-                    self.pending_load_image = true;
+                    self.load_image();
                 }
             }
             _ => {}
         }
     }
 
+    fn move_grid_selection(&mut self, key_code: KeyCode) {
+        let last = self.search_results.len().saturating_sub(1);
+        let columns = self.grid_state.columns.max(1);
+        let current = self.list_state.selected().unwrap_or(0);
+
+        let next = match key_code {
+            KeyCode::Left => current.saturating_sub(1),
+            KeyCode::Right => (current + 1).min(last),
+            KeyCode::Up => current.checked_sub(columns).unwrap_or(current),
+            KeyCode::Down if current + columns <= last => current + columns,
+            // Land on the last cell when the row below is only partially filled.
+            KeyCode::Down if current / columns < last / columns => last,
+            _ => current,
+        };
+        self.list_state.select(Some(next));
+    }
+
+    fn update_thumbnails(&mut self) {
+        let visible = self.grid_state.visible.clone();
+        let page = self.grid_state.columns * self.grid_state.rows;
+        let keep_start = visible.start.saturating_sub(page);
+        let keep_end = (visible.end + page).min(self.search_results.len());
+
+        let visible = visible.start.min(keep_end)..visible.end.min(keep_end);
+        self.grid_state.thumbnails.request(
+            &self.search_results[visible],
+            &self.search_results[keep_start..keep_end],
+        );
+        self.grid_state.thumbnails.receive();
+    }
+
+    fn update_selection_preview(&mut self) {
+        let area = self.selection_preview.image_area;
+        let (font_width, font_height) = self.picker.font_size();
+        let target = (
+            area.width as u32 * font_width as u32,
+            area.height as u32 * font_height as u32,
+        );
+
+        let post = self
+            .list_state
+            .selected()
+            .and_then(|i| self.search_results.get(i));
+        self.selection_preview
+            .update(post, self.image_quality, target);
+    }
+
     fn handle_viewing_key(&mut self, key_code: KeyCode) {
         match key_code {
-            KeyCode::Char('q') | KeyCode::Esc => {
-                if self.search_results.is_empty() {
-                    self.state = AppState::Input;
-                    self.post = None;
-                    self.popup_state = E6PostPopupState::new();
-                } else {
-                    self.state = AppState::SearchResults;
-                    self.post = None;
-                    self.popup_state = E6PostPopupState::new();
-                }
-            }
-            KeyCode::Up => {
-                self.popup_state.scroll_offset = self.popup_state.scroll_offset.saturating_sub(1);
-            }
-            KeyCode::Down => {
-                self.popup_state.scroll_offset = self.popup_state.scroll_offset.saturating_add(1);
-            }
-            KeyCode::Char('d') => {
-                self.pending_download = true;
-            }
+            KeyCode::Char('q') | KeyCode::Esc => self.close_post(),
+            KeyCode::Up => self.popup_state.scroll(-1),
+            KeyCode::Down => self.popup_state.scroll(1),
+            KeyCode::Char('d') => self.download_post(),
             KeyCode::Char('o') => {
-                self.pending_open_browser = true;
+                if let Err(e) = self.open_in_browser() {
+                    self.error_message = Some(format!("Failed to open browser: {}", e));
+                    self.state = AppState::Error;
+                }
             }
+            KeyCode::Char('r') => self.refresh_post(),
             KeyCode::Char('f') => {
                 self.state = AppState::FullImageView;
+                self.load_image();
             }
-            _ => {}
+            KeyCode::Char('s') => self.start_slideshow(),
+            KeyCode::Char('u') => self.request_original(),
+            KeyCode::Char('n') | KeyCode::PageDown => self.show_adjacent_post(true),
+            KeyCode::Char('b') | KeyCode::PageUp => self.show_adjacent_post(false),
+            KeyCode::Char('p') => self.play_video(),
+            KeyCode::Char('v') => self.cycle_video_variant(),
+            key_code => self.handle_playback_key(key_code),
         }
     }
 
+    fn close_post(&mut self) {
+        self.tasks.cancel(TaskKind::LoadImage);
+        self.tasks.cancel(TaskKind::LoadZoomSource);
+        self.tasks.cancel(TaskKind::RefreshPost);
+        self.state = if self.search_results.is_empty() {
+            AppState::Input
+        } else {
+            AppState::SearchResults
+        };
+        self.post = None;
+        self.popup_state = E6PostPopupState::new();
+    }
+
     fn handle_full_image_key(&mut self, key_code: KeyCode) {
         match key_code {
             KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('f') => {
                 self.state = AppState::Viewing;
+                self.slideshow = None;
+            }
+            KeyCode::Char('s') if self.slideshow.is_some() => self.slideshow = None,
+            KeyCode::Char('s') => self.start_slideshow(),
+            KeyCode::Char(' ') if self.slideshow.is_some() => {
+                if let Some(ref mut slideshow) = self.slideshow {
+                    slideshow.toggle_pause();
+                }
+            }
+            KeyCode::Char('n') | KeyCode::PageDown if self.slideshow.is_some() => {
+                self.skip_slide(1);
+            }
+            KeyCode::Char('b') | KeyCode::PageUp if self.slideshow.is_some() => {
+                self.skip_slide(-1);
+            }
+            KeyCode::Char('u') => self.request_original(),
+            KeyCode::Char('n') | KeyCode::PageDown => self.show_adjacent_post(true),
+            KeyCode::Char('b') | KeyCode::PageUp => self.show_adjacent_post(false),
+            KeyCode::Char('p') => self.play_video(),
+            KeyCode::Char('v') => self.cycle_video_variant(),
+            key_code => {
+                self.handle_zoom_key(key_code);
+                self.handle_playback_key(key_code);
+            }
+        }
+    }
+
+    fn show_adjacent_post(&mut self, forward: bool) {
+        let Some(current) = self.list_state.selected() else {
+            return;
+        };
+        if self.post.is_none() || self.search_results.is_empty() {
+            return;
+        }
+
+        let target = if forward {
+            current + 1
+        } else {
+            match current.checked_sub(1) {
+                Some(target) => target,
+                None => {
+                    self.notice = Some("Already at the first post".to_string());
+                    return;
+                }
+            }
+        };
+
+        if target >= self.search_results.len() {
+            if self.can_load_more_results() {
+                self.load_next_page(true);
+                self.notice = Some("Loading more posts...".to_string());
+            } else {
+                self.notice = Some("No more posts".to_string());
             }
+            return;
+        }
+
+        self.show_post_at(target);
+    }
+
+    fn show_post_at(&mut self, target: usize) {
+        let Some(post) = self.search_results.get(target).cloned() else {
+            return;
+        };
+
+        // Hand the current image to the preloader so stepping back is instant too.
+        let previous = std::mem::replace(&mut self.popup_state, E6PostPopupState::new());
+        if let Some(ref post) = self.post
+            && let (Some(variant), Some(protocol)) =
+                (previous.image_variant, previous.image_protocol)
+        {
+            self.preloader.insert(post.id, variant, protocol);
+        }
+
+        self.list_state.select(Some(target));
+        self.post = Some(post);
+        self.load_image();
+    }
+
+    fn start_slideshow(&mut self) {
+        if self.search_results.is_empty() {
+            self.notice = Some("Slideshows need search results".to_string());
+            return;
+        }
+
+        let start = self.list_state.selected().unwrap_or(0);
+        self.slideshow = Some(Slideshow::new(
+            self.slideshow_options,
+            self.search_results.len(),
+            start,
+        ));
+        self.state = AppState::FullImageView;
+        if self.post.is_none() {
+            self.show_post_at(start);
+        } else {
+            self.load_image();
+        }
+    }
+
+    fn skip_slide(&mut self, offset: isize) {
+        let can_load_more = self.can_load_more_results();
+        let Some(ref mut slideshow) = self.slideshow else {
+            return;
+        };
+        if offset > 0 && slideshow.at_end() && can_load_more {
+            self.load_next_page(false);
+            self.notice = Some("Loading more posts...".to_string());
+            return;
+        }
+        if let Some(target) = slideshow.skip(offset) {
+            self.show_post_at(target);
+        }
+    }
+
+    fn step_slideshow(&mut self) {
+        let image_ready = match self.popup_state.image_protocol {
+            Some(ref protocol) => protocol.is_ready() || protocol.error().is_some(),
+            None => {
+                !self.tasks.is_running(TaskKind::LoadImage)
+                    && (self.popup_state.load_failed
+                        || self
+                            .post
+                            .as_ref()
+                            .is_some_and(|post| media::available_variants(post).is_empty()))
+            }
+        };
+        let animation_done = self
+            .popup_state
+            .image_protocol
+            .as_ref()
+            .is_none_or(|protocol| protocol.has_completed_loop());
+
+        let result_count = self.search_results.len();
+        let can_load_more = self.can_load_more_results();
+        let Some(ref mut slideshow) = self.slideshow else {
+            return;
+        };
+        slideshow.extend(result_count);
+
+        if !slideshow.is_due(image_ready, animation_done) {
+            return;
+        }
+        if slideshow.at_end() && can_load_more {
+            self.load_next_page(false);
+            return;
+        }
+        if let Some(target) = slideshow.skip(1) {
+            self.show_post_at(target);
+        }
+    }
+
+    fn can_load_more_results(&self) -> bool {
+        self.current_pool.is_none() && !self.results_exhausted
+    }
+
+    fn load_next_page(&mut self, advance: bool) {
+        if self.tasks.is_running(TaskKind::NextPage) {
+            return;
+        }
+
+        let page = self.next_page;
+        let tags = self.tag_input.clone();
+        let client = self.client.clone();
+        self.tasks.spawn(TaskKind::NextPage, move |_| async move {
+            let result = client
+                .search_posts_page(&tags, client.page_limit(), page)
+                .await;
+            TaskMessage::PageLoaded { advance, result }
+        });
+    }
+
+    fn append_page(&mut self, advance: bool, result: Result<Vec<E6Post>>) {
+        let page = match result {
+            Ok(page) => page,
+            Err(e) => {
+                self.notice = Some(format!("Failed to load more posts: {}", e));
+                return;
+            }
+        };
+
+        let grew = !page.is_empty();
+        self.results_exhausted = page.len() < self.client.page_limit();
+        self.next_page = PageCursor::next(&self.tag_input, self.next_page, &page);
+        self.search_results.extend(page);
+
+        if advance {
+            if grew {
+                self.show_adjacent_post(true);
+            } else {
+                self.notice = Some("No more posts".to_string());
+            }
+        }
+    }
+
+    fn preload_neighbours(&mut self) {
+        let Some(current) = self.list_state.selected() else {
+            return;
+        };
+
+        let next_slide = self.slideshow.as_ref().and_then(|s| s.peek_next());
+        let neighbours: Vec<&E6Post> = [current.checked_sub(1), Some(current + 1), next_slide]
+            .into_iter()
+            .flatten()
+            .filter_map(|i| self.search_results.get(i))
+            .collect();
+        let wanted: Vec<(&E6Post, ImageVariant)> = neighbours
+            .into_iter()
+            .filter_map(|post| Some((post, self.preload_variant(post)?)))
+            .collect();
+
+        self.preloader.request(&wanted);
+    }
+
+    fn request_original(&mut self) {
+        if self.popup_state.original_requested {
+            return;
+        }
+        self.popup_state.original_requested = true;
+        self.load_image();
+    }
+
+    fn handle_zoom_key(&mut self, key_code: KeyCode) {
+        let Some(zoom) = self
+            .popup_state
+            .image_protocol
+            .as_mut()
+            .and_then(|p| p.zoom_mut())
+        else {
+            return;
+        };
+
+        match key_code {
+            KeyCode::Char('+') | KeyCode::Char('=') => zoom.set_level(zoom.level().zoom_in()),
+            KeyCode::Char('-') => zoom.set_level(zoom.level().zoom_out()),
+            KeyCode::Char('0') => zoom.set_level(ZoomLevel::Fit),
+            KeyCode::Left | KeyCode::Char('h') => zoom.pan(-1, 0),
+            KeyCode::Right | KeyCode::Char('l') => zoom.pan(1, 0),
+            KeyCode::Up | KeyCode::Char('k') => zoom.pan(0, -1),
+            KeyCode::Down | KeyCode::Char('j') => zoom.pan(0, 1),
+            _ => {}
+        }
+
+        if zoom.level() == ZoomLevel::Fit {
+            self.tasks.cancel(TaskKind::LoadZoomSource);
+        } else if !zoom.has_source() && !self.tasks.is_running(TaskKind::LoadZoomSource) {
+            self.load_zoom_source();
+        }
+    }
+
+    fn load_zoom_source(&mut self) {
+        let Some(ref post) = self.post else {
+            return;
+        };
+        let Some(variant) = media::available_variants(post).into_iter().max() else {
+            return;
+        };
+
+        let post = post.clone();
+        let client = self.client.clone();
+        self.tasks
+            .spawn(TaskKind::LoadZoomSource, move |_| async move {
+                let result = match client.fetch_image(&post, variant).await {
+                    Ok(bytes) => tokio::task::spawn_blocking(move || {
+                        Ok(Arc::new(image::load_from_memory(&bytes)?))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(e.into())),
+                    Err(e) => Err(e),
+                };
+                TaskMessage::ZoomSourceLoaded {
+                    post_id: post.id,
+                    result,
+                }
+            });
+    }
+
+    fn show_zoom_source(&mut self, post_id: i64, result: Result<Arc<DynamicImage>>) {
+        if self.post.as_ref().is_none_or(|post| post.id != post_id) {
+            return;
+        }
+        let Some(zoom) = self
+            .popup_state
+            .image_protocol
+            .as_mut()
+            .and_then(|p| p.zoom_mut())
+        else {
+            return;
+        };
+
+        match result {
+            Ok(source) => zoom.set_source(source),
+            Err(e) => {
+                zoom.set_level(ZoomLevel::Fit);
+                self.notice = Some(format!("Failed to load image for zoom: {}", e));
+            }
+        }
+    }
+
+    fn cycle_video_variant(&mut self) {
+        let Some(ref post) = self.post else {
+            return;
+        };
+
+        let count = video::variants(post).len();
+        if count > 0 {
+            self.popup_state.selected_variant = (self.popup_state.selected_variant + 1) % count;
+        }
+    }
+
+    fn play_video(&mut self) {
+        let Some(ref post) = self.post else {
+            return;
+        };
+        if MediaKind::of(post) != MediaKind::Video {
+            return;
+        }
+
+        let variants = video::variants(post);
+        let Some(variant) = variants.get(self.popup_state.selected_variant) else {
+            self.notice = Some("No playable video variant".to_string());
+            return;
+        };
+
+        self.notice = Some(match self.player_options.launch(&variant.url) {
+            Ok(()) => format!("Playing {} in external player", variant.label),
+            Err(e) => e.to_string(),
+        });
+    }
+
+    fn handle_playback_key(&mut self, key_code: KeyCode) {
+        let Some(animation) = self
+            .popup_state
+            .image_protocol
+            .as_mut()
+            .and_then(|p| p.animation_mut())
+        else {
+            return;
+        };
+
+        match key_code {
+            KeyCode::Char(' ') => animation.toggle_pause(),
+            KeyCode::Char(',') => animation.step(-1),
+            KeyCode::Char('.') => animation.step(1),
+            KeyCode::Char('[') => animation.change_speed(false),
+            KeyCode::Char(']') => animation.change_speed(true),
             _ => {}
         }
     }
@@ -303,6 +1009,7 @@ impl App {
         match self.input_mode {
             InputMode::TagSearch => &self.tag_input,
             InputMode::PostId => &self.id_input,
+            InputMode::PoolId => &self.pool_input,
         }
     }
 
@@ -310,6 +1017,7 @@ impl App {
         match self.input_mode {
             InputMode::TagSearch => self.tag_cursor_position,
             InputMode::PostId => self.id_cursor_position,
+            InputMode::PoolId => self.pool_cursor_position,
         }
     }
 
@@ -334,10 +1042,43 @@ impl App {
                     self.id_input.insert(index, new_char);
                 }
             }
+            InputMode::PoolId => {
+                if new_char.is_ascii_digit() {
+                    self.pool_input.insert(index, new_char);
+                }
+            }
         }
         self.move_cursor_right();
     }
 
+    fn active_input_mut(&mut self) -> &mut String {
+        match self.input_mode {
+            InputMode::TagSearch => &mut self.tag_input,
+            InputMode::PostId => &mut self.id_input,
+            InputMode::PoolId => &mut self.pool_input,
+        }
+    }
+
+    fn paste(&mut self, text: &str) {
+        for c in text.chars() {
+            let c = if c.is_whitespace() { ' ' } else { c };
+            if !c.is_control() {
+                self.enter_char(c);
+            }
+        }
+    }
+
+    fn clear_input(&mut self) {
+        self.active_input_mut().clear();
+        self.set_cursor(0);
+    }
+
+    fn delete_word(&mut self) {
+        let (input, cursor) = delete_word_before(self.active_input(), self.active_cursor());
+        *self.active_input_mut() = input;
+        self.set_cursor(cursor);
+    }
+
     fn byte_index(&self) -> usize {
         self.active_input()
             .char_indices()
@@ -361,6 +1102,10 @@ impl App {
                     self.id_input.chars().take(from_left_to_current_index),
                     self.id_input.chars().skip(current_index),
                 ),
+                InputMode::PoolId => (
+                    self.pool_input.chars().take(from_left_to_current_index),
+                    self.pool_input.chars().skip(current_index),
+                ),
             };
 
             let new_input: String = before_char_to_delete.chain(after_char_to_delete).collect();
@@ -368,6 +1113,7 @@ impl App {
             match self.input_mode {
                 InputMode::TagSearch => self.tag_input = new_input,
                 InputMode::PostId => self.id_input = new_input,
+                InputMode::PoolId => self.pool_input = new_input,
             }
 
             self.move_cursor_left();
@@ -382,61 +1128,254 @@ impl App {
         match self.input_mode {
             InputMode::TagSearch => self.tag_cursor_position = pos,
             InputMode::PostId => self.id_cursor_position = pos,
+            InputMode::PoolId => self.pool_cursor_position = pos,
         }
     }
 
     fn switch_input_mode(&mut self) {
         self.input_mode = match self.input_mode {
             InputMode::TagSearch => InputMode::PostId,
+            InputMode::PostId => InputMode::PoolId,
+            InputMode::PoolId => InputMode::TagSearch,
+        };
+    }
+
+    fn switch_input_mode_back(&mut self) {
+        self.input_mode = match self.input_mode {
+            InputMode::TagSearch => InputMode::PoolId,
             InputMode::PostId => InputMode::TagSearch,
+            InputMode::PoolId => InputMode::PostId,
         };
     }
 
-    async fn search_posts(&mut self) -> Result<()> {
-        let posts = self.client.search_posts(&self.tag_input).await?;
+    fn start_search(&mut self) {
+        let tags = self.tag_input.clone();
+        let client = self.client.clone();
+        self.tasks.spawn(TaskKind::Search, move |_| async move {
+            TaskMessage::Searched(client.search_posts(&tags).await)
+        });
+    }
+
+    fn start_fetch_post(&mut self) {
+        let id = self.id_input.clone();
+        let client = self.client.clone();
+        self.tasks.spawn(TaskKind::FetchPost, move |_| async move {
+            TaskMessage::PostFetched(client.fetch_post(&id).await)
+        });
+    }
+
+    fn start_fetch_pool(&mut self) {
+        let id = self.pool_input.clone();
+        let client = self.client.clone();
+        self.tasks.spawn(TaskKind::FetchPool, move |_| async move {
+            let result = async {
+                let pool = client.fetch_pool(&id).await?;
+                let posts = client.fetch_pool_posts(&pool).await?;
+                Ok((pool, posts))
+            }
+            .await;
+            TaskMessage::PoolFetched(result)
+        });
+    }
 
+    fn show_search_results(&mut self, posts: Vec<E6Post>) {
         if posts.is_empty() {
             self.error_message = Some("No posts found for this search".to_string());
             self.state = AppState::Error;
         } else {
+            self.tasks.cancel(TaskKind::NextPage);
+            self.results_exhausted = posts.len() < self.client.page_limit();
+            self.next_page = PageCursor::next(&self.tag_input, None, &posts);
             self.search_results = posts;
+            self.current_pool = None;
+            self.grid_state.thumbnails.clear();
+            self.selection_preview.clear();
             self.state = AppState::SearchResults;
             self.list_state.select(Some(0));
         }
+    }
 
-        Ok(())
+    fn show_pool_results(&mut self, pool: E6Pool, posts: Vec<E6Post>) {
+        if posts.is_empty() {
+            self.error_message = Some(format!("Pool #{} has no visible posts", pool.id));
+            self.state = AppState::Error;
+        } else {
+            self.tasks.cancel(TaskKind::NextPage);
+            self.search_results = posts;
+            self.current_pool = Some(pool);
+            self.grid_state.thumbnails.clear();
+            self.selection_preview.clear();
+            self.state = AppState::SearchResults;
+            self.list_state.select(Some(0));
+        }
     }
 
-    async fn fetch_post(&mut self) -> Result<()> {
-        let post = self.client.fetch_post(&self.id_input).await?;
-        self.post = Some(post);
-        self.search_results.clear();
-        self.state = AppState::Viewing;
-        self.popup_state = E6PostPopupState::new();
-        Ok(())
+    fn refresh_post(&mut self) {
+        let Some(id) = self.post.as_ref().map(|post| post.id) else {
+            return;
+        };
+
+        self.client.expire_api_cache();
+        self.notice = Some("Refreshing post...".to_string());
+        let client = self.client.clone();
+        self.tasks
+            .spawn(TaskKind::RefreshPost, move |_| async move {
+                TaskMessage::PostRefreshed(client.fetch_post(&id.to_string()).await)
+            });
     }
 
-    async fn load_image(&mut self) -> Result<()> {
-        if let Some(ref post) = self.post
-            && let Some(ref url) = post.file.url
+    fn image_target_size(&self) -> (u32, u32) {
+        let (columns, rows) = self.terminal_size;
+        let (columns, rows) = if self.state == AppState::FullImageView {
+            (columns as u32, rows as u32)
+        } else {
+            // The popup preview takes the left half of the popup.
+            (
+                columns as u32 * self.ui.popup_width as u32 / 200,
+                rows as u32 * self.ui.popup_height as u32 / 100,
+            )
+        };
+
+        let (font_width, font_height) = self.picker.font_size();
+        (columns * font_width as u32, rows * font_height as u32)
+    }
+
+    fn wanted_variant(&self, post: &E6Post, original_requested: bool) -> Option<ImageVariant> {
+        if original_requested {
+            return media::available_variants(post).last().copied();
+        }
+
+        let allow_original = self.state == AppState::FullImageView;
+        media::choose_variant(
+            post,
+            self.image_quality,
+            self.image_target_size(),
+            allow_original,
+        )
+    }
+
+    fn preload_variant(&self, post: &E6Post) -> Option<ImageVariant> {
+        let variant = self.wanted_variant(post, false)?;
+        if variant == ImageVariant::Original
+            && media::may_animate(post)
+            && self.image_quality != ImageQuality::Original
+        {
+            return media::choose_variant(
+                post,
+                self.image_quality,
+                self.image_target_size(),
+                false,
+            );
+        }
+        Some(variant)
+    }
+
+    fn load_image(&mut self) {
+        let Some(ref post) = self.post else {
+            return;
+        };
+
+        let Some(variant) = self.wanted_variant(post, self.popup_state.original_requested) else {
+            return;
+        };
+        // Never swap a larger rendition for a smaller one that is already on screen.
+        if self
+            .popup_state
+            .image_variant
+            .is_some_and(|loaded| loaded >= variant)
         {
-            let img = self.client.download_image_bytes(url).await?;
-            let protocol =
-                crate::anim::protocols_from_animated_bytes(&img, &mut self.picker, 60.0)?;
+            return;
+        }
 
+        if let Some((variant, protocol)) = self.preloader.take(post.id, variant) {
+            self.tasks.cancel(TaskKind::LoadImage);
             self.popup_state.image_protocol = Some(protocol);
+            self.popup_state.image_variant = Some(variant);
+            return;
         }
 
-        Ok(())
+        if media::variant_url(post, variant).is_none() {
+            return;
+        }
+
+        let post = post.clone();
+        let client = self.client.clone();
+        let picker = self.picker.clone();
+        let options = self.animation_options.clone();
+        self.tasks.spawn(TaskKind::LoadImage, move |_| async move {
+            let result = match client.fetch_image(&post, variant).await {
+                Ok(bytes) => tokio::task::spawn_blocking(move || {
+                    anim::protocols_from_animated_bytes(bytes, &picker, options).map(Box::new)
+                })
+                .await
+                .unwrap_or_else(|e| Err(e.into())),
+                Err(e) => Err(e),
+            };
+            TaskMessage::ImageLoaded {
+                post_id: post.id,
+                variant,
+                result,
+            }
+        });
     }
 
-    async fn download_post(&mut self) -> Result<()> {
-        if let Some(ref post) = self.post {
-            self.client
-                .download_post_to_file(post, &mut self.download_progress)
-                .await?;
+    fn show_loaded_image(
+        &mut self,
+        post_id: i64,
+        variant: ImageVariant,
+        result: Result<Box<ImageProtocol>>,
+    ) {
+        if self.post.as_ref().is_none_or(|post| post.id != post_id) {
+            return;
+        }
+
+        match result {
+            Ok(protocol) => {
+                self.popup_state.image_protocol = Some(*protocol);
+                self.popup_state.image_variant = Some(variant);
+            }
+            Err(e) => {
+                self.popup_state.load_failed = true;
+                self.notice = Some(format!("Failed to load image: {}", e));
+            }
         }
-        Ok(())
+    }
+
+    fn download_post(&mut self) {
+        let Some(ref post) = self.post else {
+            return;
+        };
+        if let Some(path) = self.download_index.lookup(post) {
+            self.notice = Some(format!("Already downloaded: {}", path.display()));
+            return;
+        }
+        if self.tasks.is_running(TaskKind::Download) {
+            self.notice = Some("Another download is still running".to_string());
+            return;
+        }
+        if let Some(ref bulk) = self.bulk_download
+            && bulk.in_flight == Some(post.id)
+        {
+            self.notice = Some(format!(
+                "#{} is already being saved by download all",
+                post.id
+            ));
+            return;
+        }
+
+        self.downloading_post = Some(post.id);
+        let post = post.clone();
+        let pool = self.current_pool.clone();
+        let client = self.client.clone();
+        self.tasks
+            .spawn(TaskKind::Download, move |reporter| async move {
+                let result = client
+                    .download_post_to_file(&post, pool.as_ref(), &mut |progress| {
+                        reporter.report(TaskMessage::DownloadProgress(progress.clone()));
+                    })
+                    .await;
+                TaskMessage::Downloaded { post, result }
+            });
     }
 
     fn open_in_browser(&self) -> Result<()> {
@@ -446,4 +1385,176 @@ impl App {
         }
         Ok(())
     }
+
+    fn start_bulk_download(&mut self) {
+        if self.bulk_download.is_some() {
+            return;
+        }
+
+        let source = match self.current_pool {
+            Some(ref pool) => BulkSource::Pool(pool.clone()),
+            None => BulkSource::Tags(self.tag_input.clone()),
+        };
+
+        self.bulk_download = Some(BulkDownload::new(source));
+    }
+
+    fn cancel_bulk_download(&mut self) {
+        self.tasks.cancel(TaskKind::BulkDownload);
+        if let Some(bulk) = self.bulk_download.take() {
+            self.notice = Some(format!("Cancelled download all after {}", bulk.summary()));
+        }
+    }
+
+    fn step_bulk_download(&mut self) {
+        if self.tasks.is_running(TaskKind::BulkDownload) {
+            return;
+        }
+        let Some(ref mut bulk) = self.bulk_download else {
+            return;
+        };
+
+        if bulk.is_finished() {
+            let mut notice = format!("Download all finished: {}", bulk.summary());
+            if let Some(ref error) = bulk.last_error {
+                notice.push_str(&format!(" | last error: {}", error));
+            }
+            self.notice = Some(notice);
+            self.bulk_download = None;
+            return;
+        }
+
+        let client = self.client.clone();
+        if bulk.enumerating {
+            let query = bulk.source.query();
+            let page = bulk.next_page;
+            self.tasks
+                .spawn(TaskKind::BulkDownload, move |_| async move {
+                    TaskMessage::BulkPageListed(
+                        client.search_posts_page(&query, MAX_PAGE_LIMIT, page).await,
+                    )
+                });
+        } else {
+            let single = self
+                .downloading_post
+                .filter(|_| self.tasks.is_running(TaskKind::Download));
+            while let Some(post) = bulk.queue.pop_front() {
+                if Some(post.id) == single || self.download_index.contains(&post) {
+                    bulk.processed_posts += 1;
+                    bulk.skipped_posts += 1;
+                    bulk.total_bytes = bulk
+                        .total_bytes
+                        .saturating_sub(post.file.size.max(0) as u64);
+                    continue;
+                }
+
+                let pool = match bulk.source {
+                    BulkSource::Pool(ref pool) => Some(pool.clone()),
+                    BulkSource::Tags(_) => None,
+                };
+                bulk.in_flight = Some(post.id);
+                self.tasks
+                    .spawn(TaskKind::BulkDownload, move |_| async move {
+                        let result = client
+                            .download_post_to_file(&post, pool.as_ref(), &mut |_| {})
+                            .await;
+                        TaskMessage::BulkPostDownloaded { post, result }
+                    });
+                break;
+            }
+        }
+    }
+
+    fn enqueue_bulk_page(&mut self, result: Result<Vec<E6Post>>) {
+        let Some(ref mut bulk) = self.bulk_download else {
+            return;
+        };
+
+        match result {
+            Ok(page) => {
+                let page_len = page.len();
+                bulk.next_page = PageCursor::next(&bulk.source.query(), bulk.next_page, &page);
+                bulk.enqueue_page(page, &self.download_index);
+
+                if page_len < MAX_PAGE_LIMIT {
+                    bulk.enumerating = false;
+                    if let BulkSource::Pool(ref pool) = bulk.source {
+                        api::sort_by_pool_order(bulk.queue.make_contiguous(), pool);
+                    }
+                }
+            }
+            Err(e) => {
+                self.notice = Some(format!(
+                    "Download all stopped after {}: failed to list posts: {}",
+                    bulk.summary(),
+                    e
+                ));
+                self.bulk_download = None;
+            }
+        }
+    }
+
+    fn finish_bulk_post(&mut self, post: E6Post, result: Result<SavedDownload>) {
+        let Some(ref mut bulk) = self.bulk_download else {
+            return;
+        };
+        bulk.in_flight = None;
+
+        match result {
+            Ok(saved) => {
+                bulk.downloaded_bytes += post.file.size.max(0) as u64;
+                if let Some(e) = saved.embed_error {
+                    bulk.last_error = Some(format!("#{}: metadata not embedded: {}", post.id, e));
+                }
+                if let Err(e) = self.download_index.insert(&post, &saved.path) {
+                    bulk.last_error = Some(format!("Failed to update download index: {}", e));
+                }
+            }
+            Err(e) => {
+                bulk.failed_posts += 1;
+                bulk.last_error = Some(format!("#{}: {}", post.id, e));
+            }
+        }
+        bulk.processed_posts += 1;
+    }
+}
+
+fn delete_word_before(input: &str, cursor: usize) -> (String, usize) {
+    let chars: Vec<char> = input.chars().collect();
+    let mut start = cursor;
+    while start > 0 && chars[start - 1].is_whitespace() {
+        start -= 1;
+    }
+    while start > 0 && !chars[start - 1].is_whitespace() {
+        start -= 1;
+    }
+    (
+        chars[..start].iter().chain(&chars[cursor..]).collect(),
+        start,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deletes_the_word_before_the_cursor() {
+        assert_eq!(delete_word_before("wolf solo", 9), ("wolf ".to_string(), 5));
+        assert_eq!(
+            delete_word_before("wolf solo  ", 11),
+            ("wolf ".to_string(), 5)
+        );
+        assert_eq!(delete_word_before("wolf solo", 4), (" solo".to_string(), 0));
+        assert_eq!(
+            delete_word_before("wolf solo", 0),
+            ("wolf solo".to_string(), 0)
+        );
+        assert_eq!(delete_word_before("", 0), (String::new(), 0));
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        assert_eq!(delete_word_before("über café", 9), ("über ".to_string(), 5));
+    }
 }
@@ -0,0 +1,103 @@
+use {
+    crate::{
+        api::PageCursor,
+        index::DownloadIndex,
+        models::{E6Pool, E6Post},
+    },
+    std::collections::VecDeque,
+};
+
+#[derive(Debug, Clone)]
+pub enum BulkSource {
+    Tags(String),
+    Pool(E6Pool),
+}
+
+impl BulkSource {
+    pub fn query(&self) -> String {
+        match self {
+            BulkSource::Tags(tags) => tags.clone(),
+            BulkSource::Pool(pool) => format!("pool:{}", pool.id),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            BulkSource::Tags(tags) => format!("\"{}\"", tags),
+            BulkSource::Pool(pool) => format!("pool \"{}\"", pool.name.replace('_', " ")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BulkDownload {
+    pub source: BulkSource,
+    pub queue: VecDeque<E6Post>,
+    pub in_flight: Option<i64>,
+    pub next_page: Option<PageCursor>,
+    pub enumerating: bool,
+    pub total_posts: usize,
+    pub processed_posts: usize,
+    pub skipped_posts: usize,
+    pub failed_posts: usize,
+    pub total_bytes: u64,
+    pub downloaded_bytes: u64,
+    pub last_error: Option<String>,
+}
+
+impl BulkDownload {
+    pub fn new(source: BulkSource) -> Self {
+        Self {
+            source,
+            queue: VecDeque::new(),
+            in_flight: None,
+            next_page: None,
+            enumerating: true,
+            total_posts: 0,
+            processed_posts: 0,
+            skipped_posts: 0,
+            failed_posts: 0,
+            total_bytes: 0,
+            downloaded_bytes: 0,
+            last_error: None,
+        }
+    }
+
+    pub fn enqueue_page(&mut self, posts: Vec<E6Post>, index: &DownloadIndex) {
+        for post in posts {
+            self.total_posts += 1;
+
+            if post.file.url.is_none() || index.contains(&post) {
+                self.processed_posts += 1;
+                self.skipped_posts += 1;
+                continue;
+            }
+
+            self.total_bytes += post.file.size.max(0) as u64;
+            self.queue.push_back(post);
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.enumerating && self.queue.is_empty()
+    }
+
+    pub fn ratio(&self) -> f64 {
+        if self.total_posts == 0 {
+            0.0
+        } else {
+            self.processed_posts as f64 / self.total_posts as f64
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} of {} posts from {} ({} skipped, {} failed)",
+            self.processed_posts,
+            self.total_posts,
+            self.source.label(),
+            self.skipped_posts,
+            self.failed_posts
+        )
+    }
+}
@@ -0,0 +1,398 @@
+use {
+    crate::media::ImageVariant,
+    color_eyre::eyre::Result,
+    std::{
+        fs::{self, File},
+        path::{Path, PathBuf},
+        sync::Mutex,
+        time::SystemTime,
+    },
+};
+
+pub const DEFAULT_CACHE_SIZE_MB: u64 = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheOptions {
+    pub dir: PathBuf,
+    pub max_bytes: u64,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self {
+            dir: default_cache_dir(),
+            max_bytes: DEFAULT_CACHE_SIZE_MB * 1024 * 1024,
+        }
+    }
+}
+
+fn is_shard(name: &str) -> bool {
+    name.len() == 2 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn default_cache_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("e6tu1")
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VariantStats {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub preview: VariantStats,
+    pub sample: VariantStats,
+    pub original: VariantStats,
+}
+
+impl CacheStats {
+    pub fn total(&self) -> VariantStats {
+        VariantStats {
+            files: self.preview.files + self.sample.files + self.original.files,
+            bytes: self.preview.bytes + self.sample.bytes + self.original.bytes,
+        }
+    }
+}
+
+struct CacheEntry {
+    path: PathBuf,
+    variant: ImageVariant,
+    bytes: u64,
+    used: SystemTime,
+}
+
+/// A file's mtime doubles as its last-use time, so LRU eviction needs no index.
+pub struct ImageCache {
+    options: CacheOptions,
+    total_bytes: Mutex<u64>,
+}
+
+impl ImageCache {
+    pub fn open(options: CacheOptions) -> Self {
+        let cache = Self {
+            options,
+            total_bytes: Mutex::new(0),
+        };
+        let total = cache.entries().iter().map(|entry| entry.bytes).sum();
+        *cache.total_bytes.lock().unwrap() = total;
+        cache
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.options.dir
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.options.max_bytes
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.options.max_bytes > 0
+    }
+
+    fn path_for(&self, md5: &str, variant: ImageVariant) -> Option<PathBuf> {
+        let md5 = md5.to_ascii_lowercase();
+        if md5.len() < 2 || !md5.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(
+            self.options
+                .dir
+                .join(&md5[..2])
+                .join(format!("{}.{}", md5, variant.label())),
+        )
+    }
+
+    pub fn lookup(&self, md5: &str, variant: ImageVariant) -> Option<PathBuf> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let path = self.path_for(md5, variant)?;
+        let file = File::options().append(true).open(&path).ok()?;
+        let _ = file.set_modified(SystemTime::now());
+        Some(path)
+    }
+
+    pub fn get(&self, md5: &str, variant: ImageVariant) -> Option<Vec<u8>> {
+        fs::read(self.lookup(md5, variant)?).ok()
+    }
+
+    pub fn put(&self, md5: &str, variant: ImageVariant, bytes: &[u8]) -> Result<()> {
+        self.store(md5, variant, bytes.len() as u64, |tmp_path| {
+            fs::write(tmp_path, bytes)?;
+            Ok(())
+        })
+    }
+
+    pub fn put_file(&self, md5: &str, variant: ImageVariant, source: &Path) -> Result<()> {
+        let bytes = fs::metadata(source)?.len();
+        self.store(md5, variant, bytes, |tmp_path| {
+            fs::copy(source, tmp_path)?;
+            Ok(())
+        })
+    }
+
+    pub fn remove(&self, md5: &str, variant: ImageVariant) {
+        let Some(path) = self.path_for(md5, variant) else {
+            return;
+        };
+        if let Ok(metadata) = fs::metadata(&path)
+            && fs::remove_file(&path).is_ok()
+        {
+            let mut total = self.total_bytes.lock().unwrap();
+            *total = total.saturating_sub(metadata.len());
+        }
+    }
+
+    fn store(
+        &self,
+        md5: &str,
+        variant: ImageVariant,
+        bytes: u64,
+        write: impl FnOnce(&Path) -> Result<()>,
+    ) -> Result<()> {
+        if !self.is_enabled() || bytes > self.options.max_bytes {
+            return Ok(());
+        }
+        let Some(path) = self.path_for(md5, variant) else {
+            return Ok(());
+        };
+        if path.is_file() {
+            return Ok(());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = path.with_extension(format!("{}.tmp", variant.label()));
+        write(&tmp_path)?;
+        fs::rename(&tmp_path, &path)?;
+
+        let over_cap = {
+            let mut total = self.total_bytes.lock().unwrap();
+            *total += bytes;
+            *total > self.options.max_bytes
+        };
+        if over_cap {
+            self.evict();
+        }
+        Ok(())
+    }
+
+    fn evict(&self) {
+        let mut entries = self.entries();
+        entries.sort_by_key(|entry| entry.used);
+
+        let mut total: u64 = entries.iter().map(|entry| entry.bytes).sum();
+        for entry in entries {
+            if total <= self.options.max_bytes {
+                break;
+            }
+            if fs::remove_file(&entry.path).is_ok() {
+                total -= entry.bytes;
+            }
+        }
+        *self.total_bytes.lock().unwrap() = total;
+    }
+
+    fn entries(&self) -> Vec<CacheEntry> {
+        let mut entries = Vec::new();
+        let Ok(shards) = fs::read_dir(&self.options.dir) else {
+            return entries;
+        };
+
+        for shard in shards.filter_map(|shard| shard.ok()) {
+            if !is_shard(&shard.file_name().to_string_lossy()) {
+                continue;
+            }
+            let Ok(files) = fs::read_dir(shard.path()) else {
+                continue;
+            };
+            for file in files.filter_map(|file| file.ok()) {
+                let Ok(metadata) = file.metadata() else {
+                    continue;
+                };
+                if !metadata.is_file() {
+                    continue;
+                }
+
+                let path = file.path();
+                let variant = match path.extension().and_then(|ext| ext.to_str()) {
+                    Some("preview") => ImageVariant::Preview,
+                    Some("sample") => ImageVariant::Sample,
+                    Some("original") => ImageVariant::Original,
+                    _ => continue,
+                };
+                entries.push(CacheEntry {
+                    path,
+                    variant,
+                    bytes: metadata.len(),
+                    used: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                });
+            }
+        }
+
+        entries
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let mut stats = CacheStats::default();
+        for entry in self.entries() {
+            let bucket = match entry.variant {
+                ImageVariant::Preview => &mut stats.preview,
+                ImageVariant::Sample => &mut stats.sample,
+                ImageVariant::Original => &mut stats.original,
+            };
+            bucket.files += 1;
+            bucket.bytes += entry.bytes;
+        }
+        stats
+    }
+
+    pub fn purge(&self) -> Result<u64> {
+        let mut freed = 0;
+        for entry in self.entries() {
+            fs::remove_file(&entry.path)?;
+            freed += entry.bytes;
+            if let Some(shard) = entry.path.parent() {
+                // Fails, harmlessly, while the shard still holds other entries.
+                let _ = fs::remove_dir(shard);
+            }
+        }
+        *self.total_bytes.lock().unwrap() = 0;
+        Ok(freed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::time::{Duration, UNIX_EPOCH},
+    };
+
+    const A: &str = "aa000000000000000000000000000000";
+    const B: &str = "bb000000000000000000000000000000";
+    const C: &str = "cc000000000000000000000000000000";
+
+    fn open(name: &str, max_bytes: u64) -> ImageCache {
+        let dir = std::env::temp_dir().join(format!("e6tu1-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        ImageCache::open(CacheOptions { dir, max_bytes })
+    }
+
+    fn set_used(cache: &ImageCache, md5: &str, seconds: u64) {
+        let path = cache.path_for(md5, ImageVariant::Sample).unwrap();
+        let file = File::options().append(true).open(path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    #[test]
+    fn stores_by_md5_and_variant() {
+        let cache = open("store", 1024 * 1024);
+        cache.put(A, ImageVariant::Sample, b"sample").unwrap();
+
+        assert_eq!(
+            cache.get(A, ImageVariant::Sample).as_deref(),
+            Some(&b"sample"[..])
+        );
+        assert_eq!(cache.get(A, ImageVariant::Original), None);
+        assert_eq!(cache.get(B, ImageVariant::Sample), None);
+        assert_eq!(
+            cache.lookup(&A.to_uppercase(), ImageVariant::Sample),
+            Some(cache.dir().join("aa").join(format!("{}.sample", A)))
+        );
+
+        let stats = cache.stats();
+        assert_eq!(stats.sample, VariantStats { files: 1, bytes: 6 });
+        assert_eq!(stats.total(), VariantStats { files: 1, bytes: 6 });
+
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+
+    #[test]
+    fn ignores_unusable_md5s_and_disabled_cache() {
+        let cache = open("unusable", 1024 * 1024);
+        cache.put("", ImageVariant::Sample, b"x").unwrap();
+        cache.put("not-hex", ImageVariant::Sample, b"x").unwrap();
+        assert_eq!(cache.stats().total().files, 0);
+
+        let disabled = open("disabled", 0);
+        disabled.put(A, ImageVariant::Sample, b"x").unwrap();
+        assert_eq!(disabled.get(A, ImageVariant::Sample), None);
+
+        let _ = fs::remove_dir_all(cache.dir());
+        let _ = fs::remove_dir_all(disabled.dir());
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = open("evict", 1000);
+        cache.put(A, ImageVariant::Sample, &[0; 400]).unwrap();
+        cache.put(B, ImageVariant::Sample, &[0; 400]).unwrap();
+        set_used(&cache, A, 10);
+        set_used(&cache, B, 20);
+
+        // Reading A makes B the oldest entry.
+        assert!(cache.lookup(A, ImageVariant::Sample).is_some());
+        cache.put(C, ImageVariant::Sample, &[0; 400]).unwrap();
+
+        assert!(cache.lookup(A, ImageVariant::Sample).is_some());
+        assert!(cache.lookup(B, ImageVariant::Sample).is_none());
+        assert!(cache.lookup(C, ImageVariant::Sample).is_some());
+        assert_eq!(cache.stats().total().bytes, 800);
+
+        // Files larger than the whole cache are never stored.
+        cache.put(B, ImageVariant::Sample, &[0; 1001]).unwrap();
+        assert!(cache.lookup(B, ImageVariant::Sample).is_none());
+
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+
+    #[test]
+    fn removes_and_purges() {
+        let cache = open("purge", 1024 * 1024);
+        cache.put(A, ImageVariant::Preview, &[0; 10]).unwrap();
+        cache.put(B, ImageVariant::Original, &[0; 20]).unwrap();
+
+        cache.remove(A, ImageVariant::Preview);
+        assert!(cache.lookup(A, ImageVariant::Preview).is_none());
+
+        assert_eq!(cache.purge().unwrap(), 20);
+        assert_eq!(cache.stats().total(), VariantStats::default());
+
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+
+    #[test]
+    fn leaves_unrelated_files_alone() {
+        let cache = open("unrelated", 1000);
+        let stray_file = cache.dir().join("aa").join("notes.txt");
+        let stray_dir = cache.dir().join("Some Artist");
+        fs::create_dir_all(&stray_dir).unwrap();
+        fs::write(stray_dir.join("picture.png"), [0; 600]).unwrap();
+        fs::create_dir_all(stray_file.parent().unwrap()).unwrap();
+        fs::write(&stray_file, [0; 600]).unwrap();
+
+        cache.put(A, ImageVariant::Sample, &[0; 400]).unwrap();
+        cache.put(B, ImageVariant::Sample, &[0; 400]).unwrap();
+        cache.put(C, ImageVariant::Sample, &[0; 400]).unwrap();
+        assert_eq!(cache.stats().total().files, 2);
+
+        assert_eq!(cache.purge().unwrap(), 800);
+        assert!(stray_file.is_file());
+        assert!(stray_dir.join("picture.png").is_file());
+        assert!(!cache.dir().join("cc").exists());
+
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+}
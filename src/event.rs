@@ -1,37 +1,54 @@
 use {
+    crate::{settings::UiSettings, tasks::TaskMessage},
     color_eyre::eyre::Result,
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
-    std::time::Duration,
+    crossterm::event::{self, Event, KeyEvent, KeyEventKind, MouseEvent, MouseEventKind},
+    std::time::{Duration, Instant},
 };
 
-#[derive(Debug, Clone, Copy)]
 pub enum AppEvent {
-    Key(KeyCode),
+    Key(KeyEvent),
+    Paste(String),
+    Resize(u16, u16),
+    Mouse(MouseEvent),
     Tick,
+    Task(Box<TaskMessage>),
 }
 
 pub struct EventHandler {
     tick_rate: Duration,
+    frame_interval: Duration,
+    last_tick: Instant,
 }
 
 impl EventHandler {
-    pub fn new() -> Self {
+    pub fn new(settings: &UiSettings) -> Self {
         Self {
-            tick_rate: Duration::from_millis(100),
+            tick_rate: settings.tick_rate,
+            frame_interval: settings.frame_interval(),
+            last_tick: Instant::now(),
         }
     }
 
-    pub fn next(&self) -> Result<Option<AppEvent>> {
-        if event::poll(self.tick_rate)?
-            && let Event::Key(KeyEvent {
-                code,
-                kind: KeyEventKind::Press,
-                ..
-            }) = event::read()?
-        {
-            return Ok(Some(AppEvent::Key(code)));
+    pub fn next(&mut self) -> Result<Option<AppEvent>> {
+        // Checked before polling so a stream of input, such as pointer motion, can't starve ticks.
+        if self.last_tick.elapsed() >= self.tick_rate {
+            self.last_tick = Instant::now();
+            return Ok(Some(AppEvent::Tick));
         }
 
-        Ok(Some(AppEvent::Tick))
+        let until_tick = self.tick_rate.saturating_sub(self.last_tick.elapsed());
+        if event::poll(until_tick.min(self.frame_interval))? {
+            return Ok(match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => Some(AppEvent::Key(key)),
+                Event::Paste(text) => Some(AppEvent::Paste(text)),
+                Event::Resize(columns, rows) => Some(AppEvent::Resize(columns, rows)),
+                Event::Mouse(mouse) if mouse.kind != MouseEventKind::Moved => {
+                    Some(AppEvent::Mouse(mouse))
+                }
+                _ => None,
+            });
+        }
+
+        Ok(None)
     }
 }
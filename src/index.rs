@@ -0,0 +1,162 @@
+use {
+    crate::{
+        metadata::{self, with_suffix},
+        models::E6Post,
+    },
+    color_eyre::eyre::Result,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::HashMap,
+        ffi::OsStr,
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+pub const INDEX_FILENAME: &str = ".e6tu1-index.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub id: i64,
+    pub md5: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Default)]
+pub struct DownloadIndex {
+    dir: PathBuf,
+    by_md5: HashMap<String, IndexEntry>,
+    by_id: HashMap<i64, String>,
+}
+
+impl DownloadIndex {
+    pub fn load(dir: &Path) -> Self {
+        let mut index = Self {
+            dir: dir.to_path_buf(),
+            ..Self::default()
+        };
+
+        let entries = fs::read_to_string(dir.join(INDEX_FILENAME))
+            .ok()
+            .and_then(|json| serde_json::from_str::<Vec<IndexEntry>>(&json).ok());
+
+        match entries {
+            // Dropping deleted files once here keeps `contains` off the disk.
+            Some(entries) => entries
+                .into_iter()
+                .filter(|entry| dir.join(&entry.path).is_file())
+                .for_each(|entry| index.add(entry)),
+            None => {
+                index.scan_metadata(dir);
+                if dir.is_dir() {
+                    let _ = index.save();
+                }
+            }
+        }
+
+        index
+    }
+
+    fn scan_metadata(&mut self, dir: &Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.is_dir() {
+                self.scan_metadata(&path);
+                continue;
+            }
+
+            if path.file_name().and_then(OsStr::to_str) == Some(INDEX_FILENAME) {
+                continue;
+            }
+
+            match path.extension().and_then(OsStr::to_str) {
+                Some("json") => {}
+                Some("part") | Some("tmp") | Some("txt") => continue,
+                _ => {
+                    if !with_suffix(&path, ".json").is_file()
+                        && let Some((id, md5)) = metadata::xattrs::read_identity(&path)
+                    {
+                        self.add(IndexEntry {
+                            id,
+                            md5: md5.to_lowercase(),
+                            path: self.relative(&path),
+                        });
+                    }
+                    continue;
+                }
+            }
+
+            let media_path = path.with_extension("");
+            if !media_path.is_file() {
+                continue;
+            }
+
+            let Some(post) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|json| serde_json::from_str::<E6Post>(&json).ok())
+            else {
+                continue;
+            };
+
+            if post.id != 0 && !post.file.md5.is_empty() {
+                self.add(IndexEntry {
+                    id: post.id,
+                    md5: post.file.md5.to_lowercase(),
+                    path: self.relative(&media_path),
+                });
+            }
+        }
+    }
+
+    fn add(&mut self, entry: IndexEntry) {
+        self.by_id.insert(entry.id, entry.md5.clone());
+        self.by_md5.insert(entry.md5.clone(), entry);
+    }
+
+    fn relative(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.dir).unwrap_or(path).to_path_buf()
+    }
+
+    pub fn insert(&mut self, post: &E6Post, path: &Path) -> Result<()> {
+        self.add(IndexEntry {
+            id: post.id,
+            md5: post.file.md5.to_lowercase(),
+            path: self.relative(path),
+        });
+        self.save()
+    }
+
+    fn entry(&self, post: &E6Post) -> Option<&IndexEntry> {
+        let md5 = post.file.md5.to_lowercase();
+        self.by_md5.get(&md5).or_else(|| {
+            self.by_id
+                .get(&post.id)
+                .and_then(|md5| self.by_md5.get(md5))
+        })
+    }
+
+    pub fn lookup(&self, post: &E6Post) -> Option<PathBuf> {
+        let path = self.dir.join(&self.entry(post)?.path);
+        path.is_file().then_some(path)
+    }
+
+    pub fn contains(&self, post: &E6Post) -> bool {
+        self.entry(post).is_some()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let mut entries: Vec<&IndexEntry> = self.by_md5.values().collect();
+        entries.sort_by_key(|entry| entry.id);
+
+        let tmp_path = self.dir.join(format!("{}.tmp", INDEX_FILENAME));
+        fs::write(&tmp_path, serde_json::to_string_pretty(&entries)?)?;
+        fs::rename(tmp_path, self.dir.join(INDEX_FILENAME))?;
+        Ok(())
+    }
+}
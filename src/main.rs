@@ -1,30 +1,115 @@
-use color_eyre::eyre::Result;
+use {
+    color_eyre::eyre::{self, Result},
+    std::sync::Arc,
+};
 
 mod anim;
 mod api;
 mod app;
+mod bulk;
+mod cache;
 mod event;
+mod index;
+mod media;
+mod metadata;
 mod models;
+mod preload;
+mod preview;
+mod settings;
+mod slideshow;
+mod tasks;
+mod template;
 mod terminal;
+mod thumbnails;
 mod ui;
+mod video;
 mod widgets;
+mod zoom;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return run_command(&args);
+    }
+
+    let settings = settings::Settings::load()?;
+
+    let cache = Arc::new(cache::ImageCache::open(settings.cache.clone()));
+
     let mut terminal = terminal::init()?;
-    let mut app = app::App::new();
-    let mut event_handler = event::EventHandler::new();
+    let mut app = app::App::new(&settings, cache);
+    let mut event_handler = event::EventHandler::new(&settings.ui);
 
-    let result = run_app(&mut terminal, &mut app, &mut event_handler).await;
+    let result = run_app(&mut terminal, &mut app, &mut event_handler);
 
     terminal::restore()?;
 
     result
 }
 
-async fn run_app(
+fn run_command(args: &[String]) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["cache", "stats"] => {
+            let cache = cache::ImageCache::open(cache_options()?);
+            let stats = cache.stats();
+            let megabytes = |bytes: u64| bytes as f64 / 1_048_576.0;
+
+            println!("Cache directory: {}", cache.dir().display());
+            for (label, entry) in [
+                ("preview", stats.preview),
+                ("sample", stats.sample),
+                ("original", stats.original),
+                ("total", stats.total()),
+            ] {
+                println!(
+                    "{:>9}: {:>6} files, {:>10.2} MB",
+                    label,
+                    entry.files,
+                    megabytes(entry.bytes)
+                );
+            }
+            println!("    limit: {:>25.2} MB", megabytes(cache.max_bytes()));
+            Ok(())
+        }
+        ["cache", "purge"] => {
+            let cache = cache::ImageCache::open(cache_options()?);
+            let freed = cache.purge()?;
+            println!(
+                "Removed {:.2} MB from {}",
+                freed as f64 / 1_048_576.0,
+                cache.dir().display()
+            );
+            Ok(())
+        }
+        ["config"] => {
+            let settings = settings::Settings::load()?;
+            let status = if settings.path.is_file() {
+                "loaded"
+            } else {
+                "not found, using defaults"
+            };
+            println!("# {} ({})", settings.path.display(), status);
+            println!("{}", settings);
+            Ok(())
+        }
+        _ => eyre::bail!("Usage: e6tu1 [cache stats | cache purge | config]"),
+    }
+}
+
+fn cache_options() -> Result<cache::CacheOptions> {
+    settings::Settings::load()
+        .map(|settings| settings.cache)
+        .or_else(|e| {
+            eprintln!("Warning: {}", e);
+            settings::Settings::load_cache_options()
+        })
+}
+
+fn run_app(
     terminal: &mut terminal::Terminal,
     app: &mut app::App,
     event_handler: &mut event::EventHandler,
@@ -32,10 +117,13 @@ async fn run_app(
     loop {
         terminal.draw(|f| ui::render(f, app))?;
 
-        app.process_pending_operations().await?;
+        while let Some(message) = app.tasks.try_recv() {
+            app.handle_event(event::AppEvent::Task(Box::new(message)))?;
+        }
+        app.update();
 
         if let Some(event) = event_handler.next()?
-            && !app.handle_event(event).await?
+            && !app.handle_event(event)?
         {
             return Ok(());
         }
@@ -0,0 +1,218 @@
+use {
+    crate::models::E6Post,
+    color_eyre::eyre::{self, Result},
+    std::{fmt, str::FromStr},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Video,
+    Unsupported,
+}
+
+impl MediaKind {
+    pub fn of(post: &E6Post) -> Self {
+        match post.file.ext.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" | "png" | "gif" | "webp" => MediaKind::Image,
+            "webm" | "mp4" => MediaKind::Video,
+            _ => MediaKind::Unsupported,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ImageVariant {
+    Preview,
+    Sample,
+    Original,
+}
+
+impl ImageVariant {
+    pub fn label(self) -> &'static str {
+        match self {
+            ImageVariant::Preview => "preview",
+            ImageVariant::Sample => "sample",
+            ImageVariant::Original => "original",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageQuality {
+    Preview,
+    Sample,
+    Original,
+    #[default]
+    Auto,
+}
+
+impl FromStr for ImageQuality {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "preview" => Ok(ImageQuality::Preview),
+            "sample" => Ok(ImageQuality::Sample),
+            "original" => Ok(ImageQuality::Original),
+            "auto" => Ok(ImageQuality::Auto),
+            other => eyre::bail!(
+                "Unknown image quality \"{}\" (expected preview, sample, original or auto)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ImageQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ImageQuality::Preview => "preview",
+            ImageQuality::Sample => "sample",
+            ImageQuality::Original => "original",
+            ImageQuality::Auto => "auto",
+        })
+    }
+}
+
+pub fn variant_url(post: &E6Post, variant: ImageVariant) -> Option<&str> {
+    match variant {
+        ImageVariant::Preview => post.preview.url.as_deref(),
+        ImageVariant::Sample => post.sample.url.as_deref().filter(|_| post.sample.has),
+        ImageVariant::Original => post.file.url.as_deref(),
+    }
+}
+
+fn variant_size(post: &E6Post, variant: ImageVariant) -> (i64, i64) {
+    match variant {
+        ImageVariant::Preview => (post.preview.width, post.preview.height),
+        ImageVariant::Sample => (post.sample.width, post.sample.height),
+        ImageVariant::Original => (post.file.width, post.file.height),
+    }
+}
+
+/// Previews and samples are always static JPEGs, so only the original can play.
+pub fn may_animate(post: &E6Post) -> bool {
+    match post.file.ext.to_ascii_lowercase().as_str() {
+        "gif" => true,
+        "png" | "webp" => post
+            .tags
+            .meta
+            .iter()
+            .chain(&post.tags.general)
+            .any(|tag| tag.starts_with("animated")),
+        _ => false,
+    }
+}
+
+pub fn available_variants(post: &E6Post) -> Vec<ImageVariant> {
+    let candidates: &[ImageVariant] = match MediaKind::of(post) {
+        MediaKind::Image => &[
+            ImageVariant::Preview,
+            ImageVariant::Sample,
+            ImageVariant::Original,
+        ],
+        MediaKind::Video => &[ImageVariant::Preview, ImageVariant::Sample],
+        MediaKind::Unsupported => &[ImageVariant::Preview],
+    };
+
+    candidates
+        .iter()
+        .copied()
+        .filter(|&variant| variant_url(post, variant).is_some())
+        .collect()
+}
+
+pub fn choose_variant(
+    post: &E6Post,
+    quality: ImageQuality,
+    target: (u32, u32),
+    allow_original: bool,
+) -> Option<ImageVariant> {
+    let variants: Vec<ImageVariant> = available_variants(post)
+        .into_iter()
+        .filter(|&variant| {
+            variant != ImageVariant::Original || allow_original || quality == ImageQuality::Original
+        })
+        .collect();
+
+    if allow_original && may_animate(post) && variants.contains(&ImageVariant::Original) {
+        return Some(ImageVariant::Original);
+    }
+
+    let wanted = match quality {
+        ImageQuality::Preview => ImageVariant::Preview,
+        ImageQuality::Sample => ImageVariant::Sample,
+        ImageQuality::Original => ImageVariant::Original,
+        ImageQuality::Auto => {
+            // A variant is enough once fitting it into the viewer no longer scales it up.
+            let covers = |&&variant: &&ImageVariant| {
+                let (width, height) = variant_size(post, variant);
+                width >= target.0 as i64 || height >= target.1 as i64
+            };
+            return variants.iter().find(covers).or(variants.last()).copied();
+        }
+    };
+
+    variants
+        .iter()
+        .rev()
+        .find(|&&variant| variant <= wanted)
+        .or(variants.first())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animated_gif() -> E6Post {
+        let mut post = E6Post::default();
+        post.file.ext = "gif".to_string();
+        post.file.url = Some("https://example.net/original.gif".to_string());
+        (post.file.width, post.file.height) = (1600, 1200);
+        post.sample.has = true;
+        post.sample.url = Some("https://example.net/sample.jpg".to_string());
+        (post.sample.width, post.sample.height) = (850, 637);
+        post.preview.url = Some("https://example.net/preview.jpg".to_string());
+        (post.preview.width, post.preview.height) = (150, 112);
+        post
+    }
+
+    #[test]
+    fn animated_originals_only_in_full_view() {
+        let post = animated_gif();
+        for quality in [ImageQuality::Auto, ImageQuality::Sample] {
+            assert_eq!(
+                choose_variant(&post, quality, (800, 600), false),
+                Some(ImageVariant::Sample)
+            );
+            assert_eq!(
+                choose_variant(&post, quality, (800, 600), true),
+                Some(ImageVariant::Original)
+            );
+        }
+        assert_eq!(
+            choose_variant(&post, ImageQuality::Original, (100, 100), false),
+            Some(ImageVariant::Original)
+        );
+    }
+
+    #[test]
+    fn still_images_follow_the_target_size() {
+        let mut post = animated_gif();
+        post.file.ext = "jpg".to_string();
+        assert_eq!(
+            choose_variant(&post, ImageQuality::Auto, (800, 600), true),
+            Some(ImageVariant::Sample)
+        );
+        assert_eq!(
+            choose_variant(&post, ImageQuality::Auto, (2000, 1500), true),
+            Some(ImageVariant::Original)
+        );
+        assert_eq!(
+            choose_variant(&post, ImageQuality::Auto, (2000, 1500), false),
+            Some(ImageVariant::Sample)
+        );
+    }
+}
@@ -0,0 +1,95 @@
+pub mod sidecar;
+pub mod xattrs;
+pub mod xmp;
+
+use {
+    crate::models::E6Post,
+    color_eyre::eyre::{self, Result},
+    sidecar::SidecarFormat,
+    std::{
+        fmt,
+        path::{Path, PathBuf},
+        str::FromStr,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataMode {
+    Sidecar,
+    Xattr,
+    Both,
+    None,
+}
+
+impl Default for MetadataMode {
+    fn default() -> Self {
+        if cfg!(target_os = "windows") {
+            MetadataMode::Xattr
+        } else {
+            MetadataMode::Sidecar
+        }
+    }
+}
+
+impl MetadataMode {
+    pub fn writes_sidecar(self) -> bool {
+        matches!(self, MetadataMode::Sidecar | MetadataMode::Both)
+    }
+
+    pub fn writes_xattr(self) -> bool {
+        matches!(self, MetadataMode::Xattr | MetadataMode::Both)
+    }
+}
+
+impl FromStr for MetadataMode {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "sidecar" => Ok(MetadataMode::Sidecar),
+            "xattr" => Ok(MetadataMode::Xattr),
+            "both" => Ok(MetadataMode::Both),
+            "none" => Ok(MetadataMode::None),
+            other => eyre::bail!(
+                "Unknown metadata mode \"{}\" (expected sidecar, xattr, both or none)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for MetadataMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MetadataMode::Sidecar => "sidecar",
+            MetadataMode::Xattr => "xattr",
+            MetadataMode::Both => "both",
+            MetadataMode::None => "none",
+        })
+    }
+}
+
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+pub fn save_metadata(
+    post: &E6Post,
+    path: &Path,
+    mode: MetadataMode,
+    sidecar_formats: &[SidecarFormat],
+) -> Result<()> {
+    let mut write_sidecar = mode.writes_sidecar();
+
+    if mode.writes_xattr() && xattrs::write(post, path).is_err() {
+        write_sidecar = true;
+    }
+
+    if write_sidecar {
+        sidecar::write_sidecars(post, path, sidecar_formats)?;
+    }
+
+    Ok(())
+}
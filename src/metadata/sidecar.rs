@@ -0,0 +1,349 @@
+use {
+    super::with_suffix,
+    crate::models::E6Post,
+    color_eyre::eyre::{self, Result},
+    std::{
+        fmt,
+        fs::File,
+        io::Write,
+        path::{Path, PathBuf},
+        str::FromStr,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidecarFormat {
+    Native,
+    GalleryDl,
+    Hydrus,
+    Tags,
+}
+
+impl SidecarFormat {
+    pub fn path_for(self, path: &Path) -> PathBuf {
+        match self {
+            SidecarFormat::Native | SidecarFormat::GalleryDl => with_suffix(path, ".json"),
+            SidecarFormat::Hydrus => with_suffix(path, ".txt"),
+            SidecarFormat::Tags => with_suffix(path, ".tags.txt"),
+        }
+    }
+
+    pub fn parse_list(list: &str) -> Result<Vec<Self>> {
+        let mut formats = Vec::new();
+        for format in list.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let format = format.parse()?;
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        Ok(formats)
+    }
+}
+
+impl FromStr for SidecarFormat {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "native" | "json" => Ok(SidecarFormat::Native),
+            "gallery-dl" | "gallerydl" => Ok(SidecarFormat::GalleryDl),
+            "hydrus" => Ok(SidecarFormat::Hydrus),
+            "tags" | "txt" => Ok(SidecarFormat::Tags),
+            other => eyre::bail!(
+                "Unknown sidecar format \"{}\" (expected native, gallery-dl, hydrus or tags)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SidecarFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SidecarFormat::Native => "native",
+            SidecarFormat::GalleryDl => "gallery-dl",
+            SidecarFormat::Hydrus => "hydrus",
+            SidecarFormat::Tags => "tags",
+        })
+    }
+}
+
+pub fn write_sidecars(post: &E6Post, path: &Path, formats: &[SidecarFormat]) -> Result<()> {
+    for &format in formats {
+        // The gallery-dl document is a superset of the native dump and shares its file name.
+        if format == SidecarFormat::Native && formats.contains(&SidecarFormat::GalleryDl) {
+            continue;
+        }
+
+        let contents = match format {
+            SidecarFormat::Native => serde_json::to_string_pretty(post)?,
+            SidecarFormat::GalleryDl => gallery_dl_json(post, path)?,
+            SidecarFormat::Hydrus => hydrus_tags(post).join("\n"),
+            SidecarFormat::Tags => all_tags(post).join("\n"),
+        };
+
+        let mut file = File::create(format.path_for(path))?;
+        file.write_all(contents.as_bytes())?;
+        file.flush()?;
+    }
+
+    Ok(())
+}
+
+fn all_tags(post: &E6Post) -> Vec<String> {
+    let tags = &post.tags;
+    tags.artist
+        .iter()
+        .chain(&tags.copyright)
+        .chain(&tags.character)
+        .chain(&tags.species)
+        .chain(&tags.general)
+        .chain(&tags.lore)
+        .chain(&tags.meta)
+        .cloned()
+        .collect()
+}
+
+fn hydrus_tags(post: &E6Post) -> Vec<String> {
+    let tags = &post.tags;
+    let namespaced = [
+        ("creator:", &tags.artist),
+        ("series:", &tags.copyright),
+        ("character:", &tags.character),
+        ("species:", &tags.species),
+        ("lore:", &tags.lore),
+        ("meta:", &tags.meta),
+        ("", &tags.general),
+    ];
+
+    let mut lines: Vec<String> = namespaced
+        .iter()
+        .flat_map(|(namespace, tags)| {
+            tags.iter()
+                .map(move |tag| format!("{}{}", namespace, tag.replace('_', " ")))
+        })
+        .collect();
+
+    let rating = match post.rating.as_str() {
+        "s" => Some("safe"),
+        "q" => Some("questionable"),
+        "e" => Some("explicit"),
+        _ => None,
+    };
+    if let Some(rating) = rating {
+        lines.push(format!("rating:{}", rating));
+    }
+
+    lines
+}
+
+fn gallery_dl_json(post: &E6Post, path: &Path) -> Result<String> {
+    let mut value = serde_json::to_value(post)?;
+
+    if let Some(object) = value.as_object_mut() {
+        let filename = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(&post.file.md5)
+            .to_string();
+
+        object.insert("category".to_string(), "e621".into());
+        object.insert("subcategory".to_string(), "post".into());
+        object.insert("filename".to_string(), filename.into());
+        object.insert("extension".to_string(), post.file.ext.clone().into());
+        if let Some(date) = utc_date(&post.created_at) {
+            object.insert("date".to_string(), date.into());
+        }
+    }
+
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+fn utc_date(timestamp: &str) -> Option<String> {
+    let number = |range: std::ops::Range<usize>| timestamp.get(range)?.parse::<i64>().ok();
+
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    let offset_start = timestamp[19..].find(['+', '-', 'Z']).map(|i| i + 19);
+    let offset_seconds = match offset_start {
+        Some(i) if &timestamp[i..i + 1] != "Z" => {
+            let sign = if &timestamp[i..i + 1] == "-" { -1 } else { 1 };
+            let hours = number(i + 1..i + 3)?;
+            let minutes = number(i + 4..i + 6).unwrap_or(0);
+            sign * (hours * 3600 + minutes * 60)
+        }
+        _ => 0,
+    };
+
+    let days = days_from_civil(year, month, day);
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset_seconds;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let time = seconds.rem_euclid(86_400);
+
+    Some(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    ))
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::fs};
+
+    fn post() -> E6Post {
+        let mut post = E6Post {
+            id: 7,
+            rating: "q".to_string(),
+            created_at: "2024-03-01T12:30:00.000-05:00".to_string(),
+            ..Default::default()
+        };
+        let tags = &mut post.tags;
+        tags.artist.push("an_artist".to_string());
+        tags.copyright.push("a_series".to_string());
+        tags.character.push("a_character".to_string());
+        tags.species.push("fox".to_string());
+        tags.general.push("long_tail".to_string());
+        tags.lore.push("a_lore".to_string());
+        tags.meta.push("hi_res".to_string());
+        post
+    }
+
+    #[test]
+    fn hydrus_tags_are_namespaced() {
+        assert_eq!(
+            hydrus_tags(&post()),
+            [
+                "creator:an artist",
+                "series:a series",
+                "character:a character",
+                "species:fox",
+                "lore:a lore",
+                "meta:hi res",
+                "long tail",
+                "rating:questionable",
+            ]
+        );
+    }
+
+    #[test]
+    fn hydrus_ratings() {
+        let rating = |rating: &str| {
+            let post = E6Post {
+                rating: rating.to_string(),
+                ..Default::default()
+            };
+            hydrus_tags(&post)
+        };
+        assert_eq!(rating("s"), ["rating:safe"]);
+        assert_eq!(rating("q"), ["rating:questionable"]);
+        assert_eq!(rating("e"), ["rating:explicit"]);
+        assert!(rating("").is_empty());
+    }
+
+    #[test]
+    fn converts_timestamps_to_utc() {
+        let date = |timestamp: &str| utc_date(timestamp);
+        assert_eq!(
+            date("2024-03-01T12:30:15Z").as_deref(),
+            Some("2024-03-01 12:30:15")
+        );
+        assert_eq!(
+            date("2024-03-01T12:30:15.123+02:00").as_deref(),
+            Some("2024-03-01 10:30:15")
+        );
+        assert_eq!(
+            date("2024-02-29T22:30:00.5-05:00").as_deref(),
+            Some("2024-03-01 03:30:00")
+        );
+        assert_eq!(
+            date("2023-12-31T20:00:00-04:30").as_deref(),
+            Some("2024-01-01 00:30:00")
+        );
+        assert_eq!(
+            date("2024-01-01T01:00:00+05:00").as_deref(),
+            Some("2023-12-31 20:00:00")
+        );
+        assert_eq!(
+            date("2024-01-01T01:00:00").as_deref(),
+            Some("2024-01-01 01:00:00")
+        );
+        assert_eq!(date("yesterday"), None);
+    }
+
+    #[test]
+    fn civil_days_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        for days in [-719_468, -1, 0, 11_016, 19_782, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn parses_format_lists() {
+        assert_eq!(
+            SidecarFormat::parse_list(" native, hydrus,json ,, tags").unwrap(),
+            [
+                SidecarFormat::Native,
+                SidecarFormat::Hydrus,
+                SidecarFormat::Tags
+            ]
+        );
+        assert_eq!(SidecarFormat::parse_list("").unwrap(), []);
+        let error = SidecarFormat::parse_list("native,xml").unwrap_err();
+        assert!(error.to_string().contains("\"xml\""));
+    }
+
+    #[test]
+    fn gallery_dl_replaces_the_native_dump() {
+        let dir = std::env::temp_dir().join(format!("e6tu1-sidecar-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("7.png");
+
+        write_sidecars(
+            &post(),
+            &path,
+            &[SidecarFormat::Native, SidecarFormat::GalleryDl],
+        )
+        .unwrap();
+        let json = fs::read_to_string(SidecarFormat::Native.path_for(&path)).unwrap();
+        assert!(json.contains("\"category\": \"e621\""));
+        assert!(json.contains("\"date\": \"2024-03-01 17:30:00\""));
+
+        write_sidecars(&post(), &path, &[SidecarFormat::Native]).unwrap();
+        let json = fs::read_to_string(SidecarFormat::Native.path_for(&path)).unwrap();
+        assert!(!json.contains("\"category\""));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
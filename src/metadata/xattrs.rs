@@ -0,0 +1,85 @@
+use {
+    crate::{api::BASE_URL, models::E6Post},
+    color_eyre::eyre::Result,
+    std::path::Path,
+};
+
+#[cfg(unix)]
+const ATTR_ORIGIN_URL: &str = "user.xdg.origin.url";
+#[cfg(unix)]
+const ATTR_TAGS: &str = "user.xdg.tags";
+#[cfg(unix)]
+const ATTR_ID: &str = "user.e621.id";
+#[cfg(unix)]
+const ATTR_MD5: &str = "user.e621.md5";
+#[cfg(unix)]
+const ATTR_RATING: &str = "user.e621.rating";
+#[cfg(unix)]
+const ATTR_SOURCES: &str = "user.e621.sources";
+
+#[cfg(unix)]
+pub fn write(post: &E6Post, path: &Path) -> Result<()> {
+    let tags = &post.tags;
+    let all_tags: Vec<&str> = tags
+        .artist
+        .iter()
+        .chain(&tags.copyright)
+        .chain(&tags.character)
+        .chain(&tags.species)
+        .chain(&tags.general)
+        .chain(&tags.lore)
+        .chain(&tags.meta)
+        .map(String::as_str)
+        .collect();
+
+    let origin_url = format!("{}/posts/{}", BASE_URL, post.id);
+
+    xattr::set(path, ATTR_ID, post.id.to_string().as_bytes())?;
+    xattr::set(path, ATTR_MD5, post.file.md5.as_bytes())?;
+    xattr::set(path, ATTR_RATING, post.rating.as_bytes())?;
+    xattr::set(path, ATTR_ORIGIN_URL, origin_url.as_bytes())?;
+    xattr::set(path, ATTR_TAGS, all_tags.join(",").as_bytes())?;
+    if !post.sources.is_empty() {
+        xattr::set(path, ATTR_SOURCES, post.sources.join("\n").as_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn read_identity(path: &Path) -> Option<(i64, String)> {
+    let id = xattr::get(path, ATTR_ID).ok()??;
+    let md5 = xattr::get(path, ATTR_MD5).ok()??;
+
+    let id = String::from_utf8(id).ok()?.parse().ok()?;
+    let md5 = String::from_utf8(md5).ok()?;
+    Some((id, md5))
+}
+
+#[cfg(target_os = "windows")]
+pub fn write(post: &E6Post, path: &Path) -> Result<()> {
+    use std::{fs::File, io::Write};
+
+    let metadata_json = serde_json::to_string_pretty(post)?;
+    let mut ads_file = File::create(super::with_suffix(path, ":metadata"))?;
+    ads_file.write_all(metadata_json.as_bytes())?;
+    ads_file.flush()?;
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+pub fn read_identity(path: &Path) -> Option<(i64, String)> {
+    let json = std::fs::read_to_string(super::with_suffix(path, ":metadata")).ok()?;
+    let post: E6Post = serde_json::from_str(&json).ok()?;
+    Some((post.id, post.file.md5))
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+pub fn write(_post: &E6Post, _path: &Path) -> Result<()> {
+    color_eyre::eyre::bail!("Extended attributes are not supported on this platform")
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+pub fn read_identity(_path: &Path) -> Option<(i64, String)> {
+    None
+}
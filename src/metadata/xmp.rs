@@ -0,0 +1,429 @@
+use {
+    super::with_suffix,
+    crate::{api::BASE_URL, models::E6Post},
+    color_eyre::eyre::{self, Result},
+    std::{fs, path::Path},
+};
+
+const JPEG_XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+const WEBP_XMP_FLAG: u8 = 0x04;
+const WEBP_ALPHA_FLAG: u8 = 0x10;
+
+pub fn supports(ext: &str) -> bool {
+    matches!(
+        ext.to_ascii_lowercase().as_str(),
+        "jpg" | "jpeg" | "png" | "webp"
+    )
+}
+
+pub fn embed(post: &E6Post, path: &Path) -> Result<()> {
+    if !supports(&post.file.ext) {
+        return Ok(());
+    }
+
+    let bytes = fs::read(path)?;
+    let packet = build_packet(post);
+
+    let embedded = if bytes.starts_with(&[0xFF, 0xD8]) {
+        embed_jpeg(&bytes, &packet)?
+    } else if bytes.starts_with(PNG_SIGNATURE) {
+        embed_png(&bytes, &packet)?
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        embed_webp(&bytes, &packet)?
+    } else {
+        eyre::bail!("Unrecognized {} file layout", post.file.ext);
+    };
+
+    let tmp_path = with_suffix(path, ".tmp");
+    fs::write(&tmp_path, embedded)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if c.is_control() && c != '\n' && c != '\t' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn rdf_list(container: &str, items: &[&str]) -> String {
+    let mut list = format!("<rdf:{}>", container);
+    for item in items {
+        list.push_str(&format!("<rdf:li>{}</rdf:li>", escape(item)));
+    }
+    list.push_str(&format!("</rdf:{}>", container));
+    list
+}
+
+fn build_packet(post: &E6Post) -> Vec<u8> {
+    let tags = &post.tags;
+    let subjects: Vec<&str> = tags
+        .artist
+        .iter()
+        .chain(&tags.copyright)
+        .chain(&tags.character)
+        .chain(&tags.species)
+        .chain(&tags.general)
+        .chain(&tags.lore)
+        .chain(&tags.meta)
+        .map(String::as_str)
+        .collect();
+    let creators: Vec<&str> = tags.artist.iter().map(String::as_str).collect();
+    let sources: Vec<&str> = post.sources.iter().map(String::as_str).collect();
+    let post_url = format!("{}/posts/{}", BASE_URL, post.id);
+
+    let mut description = String::new();
+    description.push_str(&format!(
+        "<dc:subject>{}</dc:subject>",
+        rdf_list("Bag", &subjects)
+    ));
+    if !creators.is_empty() {
+        description.push_str(&format!(
+            "<dc:creator>{}</dc:creator>",
+            rdf_list("Seq", &creators)
+        ));
+    }
+    description.push_str(&format!("<dc:source>{}</dc:source>", escape(&post_url)));
+    description.push_str(&format!("<dc:identifier>e621:{}</dc:identifier>", post.id));
+    if !sources.is_empty() {
+        description.push_str(&format!(
+            "<dc:relation>{}</dc:relation>",
+            rdf_list("Bag", &sources)
+        ));
+    }
+    description.push_str(&format!("<e621:PostID>{}</e621:PostID>", post.id));
+    description.push_str(&format!(
+        "<e621:Rating>{}</e621:Rating>",
+        escape(&post.rating)
+    ));
+    description.push_str(&format!(
+        "<e621:OriginalMD5>{}</e621:OriginalMD5>",
+        escape(&post.file.md5)
+    ));
+
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\
+         <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+         <rdf:Description rdf:about=\"\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+         xmlns:e621=\"https://e621.net/xmp/1.0/\">\
+         {}\
+         </rdf:Description>\
+         </rdf:RDF>\
+         </x:xmpmeta>\
+         <?xpacket end=\"w\"?>",
+        description
+    )
+    .into_bytes()
+}
+
+fn embed_jpeg(bytes: &[u8], packet: &[u8]) -> Result<Vec<u8>> {
+    let segment_len = JPEG_XMP_SIGNATURE.len() + packet.len() + 2;
+    if segment_len > u16::MAX as usize {
+        eyre::bail!("XMP packet is too large for a JPEG APP1 segment");
+    }
+
+    let mut xmp_segment = vec![0xFF, 0xE1];
+    xmp_segment.extend_from_slice(&(segment_len as u16).to_be_bytes());
+    xmp_segment.extend_from_slice(JPEG_XMP_SIGNATURE);
+    xmp_segment.extend_from_slice(packet);
+
+    let mut out = Vec::with_capacity(bytes.len() + xmp_segment.len());
+    out.extend_from_slice(&bytes[..2]);
+
+    let mut pos = 2;
+    let mut inserted = false;
+
+    while pos < bytes.len() {
+        if bytes[pos] != 0xFF {
+            eyre::bail!("Malformed JPEG marker at offset {}", pos);
+        }
+
+        let marker = bytes.get(pos + 1).copied().unwrap_or(0);
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            out.extend_from_slice(&bytes[pos..pos + 2]);
+            pos += 2;
+            continue;
+        }
+
+        let is_leading_app = marker == 0xE0 || marker == 0xE1;
+        if !inserted && !is_leading_app {
+            out.extend_from_slice(&xmp_segment);
+            inserted = true;
+        }
+
+        if marker == 0xDA || marker == 0xD9 {
+            out.extend_from_slice(&bytes[pos..]);
+            return Ok(out);
+        }
+
+        let len = bytes
+            .get(pos + 2..pos + 4)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(|| eyre::Error::msg("Truncated JPEG segment"))?;
+        let end = pos + 2 + len;
+        if len < 2 || end > bytes.len() {
+            eyre::bail!("Truncated JPEG segment at offset {}", pos);
+        }
+
+        let payload = &bytes[pos + 4..end];
+        if !(marker == 0xE1 && payload.starts_with(JPEG_XMP_SIGNATURE)) {
+            out.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+
+    eyre::bail!("JPEG has no image data")
+}
+
+fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut chunk = Vec::with_capacity(data.len() + 12);
+    chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    chunk.extend_from_slice(kind);
+    chunk.extend_from_slice(data);
+
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(data);
+    chunk.extend_from_slice(&hasher.finalize().to_be_bytes());
+    chunk
+}
+
+fn embed_png(bytes: &[u8], packet: &[u8]) -> Result<Vec<u8>> {
+    let mut itxt = Vec::with_capacity(PNG_XMP_KEYWORD.len() + packet.len() + 5);
+    itxt.extend_from_slice(PNG_XMP_KEYWORD);
+    itxt.extend_from_slice(&[0, 0, 0, 0, 0]);
+    itxt.extend_from_slice(packet);
+    let xmp_chunk = png_chunk(b"iTXt", &itxt);
+
+    let mut out = Vec::with_capacity(bytes.len() + xmp_chunk.len());
+    out.extend_from_slice(PNG_SIGNATURE);
+
+    let mut pos = PNG_SIGNATURE.len();
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_be_bytes(bytes[pos..pos + 4].try_into()?) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let end = pos + 12 + len;
+        if end > bytes.len() {
+            eyre::bail!("Truncated PNG chunk at offset {}", pos);
+        }
+
+        let data = &bytes[pos + 8..pos + 8 + len];
+        let is_xmp = kind == b"iTXt"
+            && data.starts_with(PNG_XMP_KEYWORD)
+            && data.get(PNG_XMP_KEYWORD.len()) == Some(&0);
+
+        if !is_xmp {
+            out.extend_from_slice(&bytes[pos..end]);
+        }
+        if kind == b"IHDR" {
+            out.extend_from_slice(&xmp_chunk);
+        }
+        if kind == b"IEND" {
+            return Ok(out);
+        }
+        pos = end;
+    }
+
+    eyre::bail!("PNG has no IEND chunk")
+}
+
+fn webp_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut chunk = Vec::with_capacity(data.len() + 9);
+    chunk.extend_from_slice(kind);
+    chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        chunk.push(0);
+    }
+    chunk
+}
+
+fn webp_canvas(kind: &[u8], data: &[u8]) -> Result<(u32, u32, bool)> {
+    match kind {
+        b"VP8 " if data.len() >= 10 && data[3..6] == [0x9D, 0x01, 0x2A] => {
+            let width = u16::from_le_bytes([data[6], data[7]]) & 0x3FFF;
+            let height = u16::from_le_bytes([data[8], data[9]]) & 0x3FFF;
+            Ok((width as u32, height as u32, false))
+        }
+        b"VP8L" if data.len() >= 5 && data[0] == 0x2F => {
+            let bits = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
+            let width = (bits & 0x3FFF) + 1;
+            let height = ((bits >> 14) & 0x3FFF) + 1;
+            let alpha = (bits >> 28) & 1 == 1;
+            Ok((width, height, alpha))
+        }
+        _ => eyre::bail!("Unsupported WebP bitstream"),
+    }
+}
+
+fn embed_webp(bytes: &[u8], packet: &[u8]) -> Result<Vec<u8>> {
+    let mut chunks: Vec<(&[u8], &[u8])> = Vec::new();
+    let riff_end = (u32::from_le_bytes(bytes[4..8].try_into()?) as usize + 8).min(bytes.len());
+
+    let mut pos = 12;
+    while pos + 8 <= riff_end {
+        let kind = &bytes[pos..pos + 4];
+        let len = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into()?) as usize;
+        let end = pos + 8 + len;
+        if end > riff_end {
+            eyre::bail!("Truncated WebP chunk at offset {}", pos);
+        }
+
+        if kind != b"XMP " {
+            chunks.push((kind, &bytes[pos + 8..end]));
+        }
+        pos = end + len % 2;
+    }
+
+    let mut body = b"WEBP".to_vec();
+    match chunks.first() {
+        Some((b"VP8X", data)) if data.len() >= 10 => {
+            let mut vp8x = data.to_vec();
+            vp8x[0] |= WEBP_XMP_FLAG;
+            body.extend(webp_chunk(b"VP8X", &vp8x));
+            for (kind, data) in &chunks[1..] {
+                body.extend(webp_chunk(kind, data));
+            }
+        }
+        Some((kind, data)) => {
+            let (width, height, alpha) = webp_canvas(kind, data)?;
+            let mut vp8x = vec![
+                WEBP_XMP_FLAG | if alpha { WEBP_ALPHA_FLAG } else { 0 },
+                0,
+                0,
+                0,
+            ];
+            vp8x.extend_from_slice(&width.saturating_sub(1).to_le_bytes()[..3]);
+            vp8x.extend_from_slice(&height.saturating_sub(1).to_le_bytes()[..3]);
+            body.extend(webp_chunk(b"VP8X", &vp8x));
+            for (kind, data) in &chunks {
+                body.extend(webp_chunk(kind, data));
+            }
+        }
+        None => eyre::bail!("WebP has no image data"),
+    }
+    body.extend(webp_chunk(b"XMP ", packet));
+
+    let mut out = Vec::with_capacity(body.len() + 8);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend(body);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        image::{DynamicImage, ImageFormat, RgbaImage},
+        std::io::Cursor,
+    };
+
+    fn encode(format: ImageFormat) -> (Vec<u8>, DynamicImage) {
+        let pixels = RgbaImage::from_fn(5, 3, |x, y| {
+            image::Rgba([x as u8 * 50, y as u8 * 80, 128, 255 - x as u8 * 10])
+        });
+        let image = match format {
+            ImageFormat::Jpeg => DynamicImage::ImageRgb8(DynamicImage::from(pixels).to_rgb8()),
+            _ => DynamicImage::ImageRgba8(pixels),
+        };
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .unwrap();
+        let decoded = image::load_from_memory(&bytes).unwrap();
+        (bytes, decoded)
+    }
+
+    fn count(haystack: &[u8], needle: &[u8]) -> usize {
+        haystack
+            .windows(needle.len())
+            .filter(|window| *window == needle)
+            .count()
+    }
+
+    fn packet() -> Vec<u8> {
+        let mut post = E6Post {
+            id: 42,
+            rating: "s".to_string(),
+            ..Default::default()
+        };
+        post.tags.artist.push("some_artist".to_string());
+        post.tags.general.push("a&b".to_string());
+        build_packet(&post)
+    }
+
+    fn assert_round_trip(format: ImageFormat, embed: fn(&[u8], &[u8]) -> Result<Vec<u8>>) {
+        let (bytes, decoded) = encode(format);
+        let packet = packet();
+
+        let embedded = embed(&bytes, &packet).unwrap();
+        assert_eq!(count(&embedded, &packet), 1);
+        let reloaded = image::load_from_memory(&embedded).unwrap();
+        assert_eq!(reloaded.to_rgba8(), decoded.to_rgba8());
+
+        // Embedding again replaces the packet rather than adding a second one.
+        let again = embed(&embedded, &packet).unwrap();
+        assert_eq!(again, embedded);
+    }
+
+    #[test]
+    fn packet_escapes_tags() {
+        let packet = String::from_utf8(packet()).unwrap();
+        assert!(packet.contains("<rdf:li>a&amp;b</rdf:li>"));
+        assert!(packet.contains("<dc:creator><rdf:Seq><rdf:li>some_artist</rdf:li>"));
+        assert!(packet.contains("<e621:PostID>42</e621:PostID>"));
+    }
+
+    #[test]
+    fn embeds_into_jpeg() {
+        assert_round_trip(ImageFormat::Jpeg, embed_jpeg);
+    }
+
+    #[test]
+    fn embeds_into_png() {
+        assert_round_trip(ImageFormat::Png, embed_png);
+    }
+
+    #[test]
+    fn embeds_into_webp() {
+        let (bytes, _) = encode(ImageFormat::WebP);
+        let embedded = embed_webp(&bytes, &packet()).unwrap();
+        assert_eq!(&embedded[12..16], b"VP8X");
+        assert_eq!(
+            embedded[20] & (WEBP_XMP_FLAG | WEBP_ALPHA_FLAG),
+            WEBP_XMP_FLAG | WEBP_ALPHA_FLAG
+        );
+        let riff_len = u32::from_le_bytes(embedded[4..8].try_into().unwrap()) as usize;
+        assert_eq!(riff_len + 8, embedded.len());
+
+        assert_round_trip(ImageFormat::WebP, embed_webp);
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let (jpeg, _) = encode(ImageFormat::Jpeg);
+        assert!(embed_jpeg(&jpeg[..20], &packet()).is_err());
+        let (png, _) = encode(ImageFormat::Png);
+        assert!(embed_png(&png[..png.len() - 12], &packet()).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use {
+    crate::{
+        anim::{self, AnimationOptions, ImageProtocol},
+        api::E621Client,
+        media::{self, ImageVariant},
+        models::E6Post,
+    },
+    ratatui_image::picker::Picker,
+    std::collections::HashMap,
+    tokio::{
+        sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+        task::AbortHandle,
+    },
+};
+
+type PreloadResult = (i64, ImageVariant, Option<ImageProtocol>);
+
+pub struct Preloader {
+    client: E621Client,
+    picker: Picker,
+    animation_options: AnimationOptions,
+    loaded: HashMap<i64, (ImageVariant, ImageProtocol)>,
+    in_flight: HashMap<i64, (ImageVariant, AbortHandle)>,
+    results_tx: UnboundedSender<PreloadResult>,
+    results_rx: UnboundedReceiver<PreloadResult>,
+}
+
+impl Preloader {
+    pub fn new(client: E621Client, picker: Picker, animation_options: AnimationOptions) -> Self {
+        let (results_tx, results_rx) = mpsc::unbounded_channel();
+        Self {
+            client,
+            picker,
+            animation_options,
+            loaded: HashMap::new(),
+            in_flight: HashMap::new(),
+            results_tx,
+            results_rx,
+        }
+    }
+
+    pub fn request(&mut self, wanted: &[(&E6Post, ImageVariant)]) {
+        let keep = |id: &i64, variant: &ImageVariant| {
+            wanted
+                .iter()
+                .any(|(post, wanted)| post.id == *id && variant >= wanted)
+        };
+        self.in_flight.retain(|id, (variant, handle)| {
+            let wanted = keep(id, variant);
+            if !wanted {
+                handle.abort();
+            }
+            wanted
+        });
+        self.loaded.retain(|id, (variant, _)| keep(id, variant));
+
+        while let Ok((id, variant, protocol)) = self.results_rx.try_recv() {
+            if self.in_flight.remove(&id).is_some()
+                && let Some(protocol) = protocol
+            {
+                self.loaded.insert(id, (variant, protocol));
+            }
+        }
+
+        for &(post, variant) in wanted {
+            if self.loaded.contains_key(&post.id) || self.in_flight.contains_key(&post.id) {
+                continue;
+            }
+            if media::variant_url(post, variant).is_none() {
+                continue;
+            }
+
+            let id = post.id;
+            let post = post.clone();
+            let client = self.client.clone();
+            let picker = self.picker.clone();
+            let options = self.animation_options.clone();
+            let results_tx = self.results_tx.clone();
+
+            let handle = tokio::spawn(async move {
+                let protocol = match client.fetch_image(&post, variant).await {
+                    Ok(bytes) => tokio::task::spawn_blocking(move || {
+                        anim::protocols_from_animated_bytes(bytes, &picker, options).ok()
+                    })
+                    .await
+                    .ok()
+                    .flatten(),
+                    Err(_) => None,
+                };
+                let _ = results_tx.send((id, variant, protocol));
+            });
+            self.in_flight.insert(id, (variant, handle.abort_handle()));
+        }
+    }
+
+    pub fn insert(&mut self, id: i64, variant: ImageVariant, protocol: ImageProtocol) {
+        if let Some((_, handle)) = self.in_flight.remove(&id) {
+            handle.abort();
+        }
+        self.loaded.insert(id, (variant, protocol));
+    }
+
+    pub fn take(
+        &mut self,
+        id: i64,
+        variant: ImageVariant,
+    ) -> Option<(ImageVariant, ImageProtocol)> {
+        match self.loaded.get(&id) {
+            Some((loaded, _)) if *loaded >= variant => self.loaded.remove(&id),
+            _ => None,
+        }
+    }
+}
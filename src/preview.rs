@@ -0,0 +1,141 @@
+use {
+    crate::{
+        anim::{self, AnimationOptions, ImageProtocol},
+        api::E621Client,
+        media::{self, ImageQuality},
+        models::E6Post,
+    },
+    ratatui::layout::Rect,
+    ratatui_image::picker::Picker,
+    std::time::{Duration, Instant},
+    tokio::{
+        sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+        task::AbortHandle,
+    },
+};
+
+pub const PREVIEW_DEBOUNCE: Duration = Duration::from_millis(250);
+
+type PreviewResult = (i64, Result<ImageProtocol, String>);
+
+pub enum PreviewImage {
+    Ready(ImageProtocol),
+    Failed(String),
+}
+
+pub struct SelectionPreview {
+    client: E621Client,
+    picker: Picker,
+    animation_options: AnimationOptions,
+    selected: Option<(i64, Instant)>,
+    loading: Option<(i64, AbortHandle)>,
+    loaded: Option<(i64, PreviewImage)>,
+    results_tx: UnboundedSender<PreviewResult>,
+    results_rx: UnboundedReceiver<PreviewResult>,
+    pub image_area: Rect,
+}
+
+impl SelectionPreview {
+    pub fn new(client: E621Client, picker: Picker, animation_options: AnimationOptions) -> Self {
+        let (results_tx, results_rx) = mpsc::unbounded_channel();
+        Self {
+            client,
+            picker,
+            animation_options,
+            selected: None,
+            loading: None,
+            loaded: None,
+            results_tx,
+            results_rx,
+            image_area: Rect::default(),
+        }
+    }
+
+    pub fn update(&mut self, post: Option<&E6Post>, quality: ImageQuality, target: (u32, u32)) {
+        let id = post.map(|post| post.id);
+        if self.selected.map(|(selected, _)| selected) != id {
+            self.selected = id.map(|id| (id, Instant::now()));
+            if self
+                .loading
+                .as_ref()
+                .is_some_and(|(loading, _)| Some(*loading) != id)
+                && let Some((_, handle)) = self.loading.take()
+            {
+                handle.abort();
+            }
+        }
+
+        while let Ok((id, result)) = self.results_rx.try_recv() {
+            if self
+                .loading
+                .as_ref()
+                .is_some_and(|(loading, _)| *loading == id)
+            {
+                self.loading = None;
+                let image = match result {
+                    Ok(protocol) => PreviewImage::Ready(protocol),
+                    Err(e) => PreviewImage::Failed(e),
+                };
+                self.loaded = Some((id, image));
+            }
+        }
+
+        let Some(post) = post else {
+            return;
+        };
+        let settled = self
+            .selected
+            .is_some_and(|(_, since)| since.elapsed() >= PREVIEW_DEBOUNCE);
+        let pending =
+            self.loading.is_some() || self.loaded.as_ref().is_some_and(|(id, _)| *id == post.id);
+        if !settled || pending {
+            return;
+        }
+
+        let Some(variant) = media::choose_variant(post, quality, target, false)
+            .filter(|&variant| media::variant_url(post, variant).is_some())
+        else {
+            self.loaded = Some((
+                post.id,
+                PreviewImage::Failed("No preview available".to_string()),
+            ));
+            return;
+        };
+
+        let id = post.id;
+        let post = post.clone();
+        let client = self.client.clone();
+        let picker = self.picker.clone();
+        let options = self.animation_options.clone();
+        let results_tx = self.results_tx.clone();
+
+        let handle = tokio::spawn(async move {
+            let result = match client.fetch_image(&post, variant).await {
+                Ok(bytes) => tokio::task::spawn_blocking(move || {
+                    anim::protocols_from_animated_bytes(bytes, &picker, options)
+                        .map_err(|e| e.to_string())
+                })
+                .await
+                .unwrap_or_else(|e| Err(e.to_string())),
+                Err(e) => Err(e.to_string()),
+            };
+            let _ = results_tx.send((id, result));
+        });
+        self.loading = Some((id, handle.abort_handle()));
+    }
+
+    pub fn image_mut(&mut self, id: i64) -> Option<&mut PreviewImage> {
+        match self.loaded {
+            Some((loaded, ref mut image)) if loaded == id => Some(image),
+            _ => None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        if let Some((_, handle)) = self.loading.take() {
+            handle.abort();
+        }
+        self.selected = None;
+        self.loaded = None;
+    }
+}
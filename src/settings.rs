@@ -0,0 +1,587 @@
+use {
+    crate::{
+        anim::AnimationOptions,
+        api::{DEFAULT_PAGE_LIMIT, DownloadOptions, MAX_PAGE_LIMIT},
+        cache::CacheOptions,
+        media::ImageQuality,
+        metadata::sidecar::SidecarFormat,
+        slideshow::SlideshowOptions,
+        template::FilenameTemplate,
+        video::PlayerOptions,
+    },
+    color_eyre::eyre::{self, Result},
+    config::{Config, ConfigError, File, FileFormat},
+    serde::{Deserialize, Serialize},
+    std::{
+        fmt,
+        path::{Path, PathBuf},
+        time::Duration,
+    },
+};
+
+pub const DEFAULT_TICK_RATE: Duration = Duration::from_millis(100);
+pub const DEFAULT_FPS: u32 = 30;
+pub const MAX_FPS: u32 = 120;
+pub const DEFAULT_POPUP_SIZE: (u16, u16) = (80, 85);
+
+const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("E6TU1_TICK_RATE_MS", "ui.tick_rate_ms"),
+    ("E6TU1_FPS", "ui.fps"),
+    ("E6TU1_POPUP_WIDTH", "ui.popup_width"),
+    ("E6TU1_POPUP_HEIGHT", "ui.popup_height"),
+    ("E6TU1_PAGE_LIMIT", "search.page_limit"),
+    ("E6TU1_DOWNLOAD_DIR", "download.dir"),
+    ("E6TU1_FILENAME_TEMPLATE", "download.filename_template"),
+    ("E6TU1_METADATA", "download.metadata"),
+    ("E6TU1_SIDECARS", "download.sidecars"),
+    ("E6TU1_EMBED_METADATA", "download.embed_metadata"),
+    ("E6TU1_CACHE_DIR", "cache.dir"),
+    ("E6TU1_CACHE_MB", "cache.size_mb"),
+    ("E6TU1_IMAGE_QUALITY", "image.quality"),
+    ("E6TU1_ANIMATION_MEMORY_MB", "image.animation_memory_mb"),
+    ("E6TU1_VIDEO_PLAYER", "video.player"),
+    ("E6TU1_SLIDESHOW_INTERVAL", "slideshow.interval"),
+    ("E6TU1_SLIDESHOW_SHUFFLE", "slideshow.shuffle"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UiSettings {
+    pub tick_rate: Duration,
+    pub fps: u32,
+    pub popup_width: u16,
+    pub popup_height: u16,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self {
+            tick_rate: DEFAULT_TICK_RATE,
+            fps: DEFAULT_FPS,
+            popup_width: DEFAULT_POPUP_SIZE.0,
+            popup_height: DEFAULT_POPUP_SIZE.1,
+        }
+    }
+}
+
+impl UiSettings {
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs(1) / self.fps
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub path: PathBuf,
+    pub ui: UiSettings,
+    pub page_limit: usize,
+    pub download: DownloadOptions,
+    pub cache: CacheOptions,
+    pub image_quality: ImageQuality,
+    pub animation: AnimationOptions,
+    pub player: PlayerOptions,
+    pub slideshow: SlideshowOptions,
+}
+
+impl Settings {
+    pub fn load() -> Result<Self> {
+        let (path, required) = config_path();
+        Self::load_from(path, required)
+    }
+
+    fn load_from(path: PathBuf, required: bool) -> Result<Self> {
+        let file: SettingsFile = build_config(Some((&path, required)))
+            .and_then(Config::try_deserialize)
+            .map_err(|e| eyre::eyre!("Invalid configuration ({}): {}", path.display(), e))?;
+
+        let mut settings = file
+            .validate()
+            .map_err(|e| eyre::eyre!("Invalid configuration ({}): {}", path.display(), e))?;
+        settings.path = path;
+        Ok(settings)
+    }
+
+    /// Reads only `[cache]`, so a mistake elsewhere in the file cannot block the cache commands.
+    pub fn load_cache_options() -> Result<CacheOptions> {
+        let (path, required) = config_path();
+        Self::load_cache_options_from(&path, required)
+    }
+
+    fn load_cache_options_from(path: &Path, required: bool) -> Result<CacheOptions> {
+        let section: CacheSection = build_config(Some((path, required)))
+            .or_else(|_| build_config(None))
+            .and_then(|config| match config.get("cache") {
+                Err(ConfigError::NotFound(_)) => Ok(CacheSection::default()),
+                result => result,
+            })
+            .map_err(|e| eyre::eyre!("Invalid configuration ({}): {}", path.display(), e))?;
+        section
+            .validate()
+            .map_err(|e| eyre::eyre!("Invalid configuration ({}): {}", path.display(), e))
+    }
+}
+
+fn config_path() -> (PathBuf, bool) {
+    match std::env::var_os("E6TU1_CONFIG") {
+        Some(path) => (PathBuf::from(path), true),
+        None => (default_config_path(), false),
+    }
+}
+
+fn build_config(file: Option<(&Path, bool)>) -> Result<Config, ConfigError> {
+    let mut builder = Config::builder();
+    if let Some((path, required)) = file {
+        builder = builder.add_source(File::from(path).format(FileFormat::Toml).required(required));
+    }
+    for (var, key) in ENV_OVERRIDES {
+        builder = builder.set_override_option(*key, std::env::var(var).ok())?;
+    }
+    builder.build()
+}
+
+fn default_config_path() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+    base.join("e6tu1").join("config.toml")
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct SettingsFile {
+    ui: UiSection,
+    search: SearchSection,
+    download: DownloadSection,
+    cache: CacheSection,
+    image: ImageSection,
+    video: VideoSection,
+    slideshow: SlideshowSection,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct UiSection {
+    tick_rate_ms: u64,
+    fps: u32,
+    popup_width: u16,
+    popup_height: u16,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct SearchSection {
+    page_limit: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct DownloadSection {
+    dir: PathBuf,
+    filename_template: String,
+    metadata: String,
+    sidecars: String,
+    embed_metadata: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct CacheSection {
+    dir: PathBuf,
+    size_mb: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct ImageSection {
+    quality: String,
+    animation_memory_mb: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct VideoSection {
+    player: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct SlideshowSection {
+    interval: f64,
+    shuffle: bool,
+}
+
+impl From<&Settings> for SettingsFile {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            ui: UiSection::from(&settings.ui),
+            search: SearchSection {
+                page_limit: settings.page_limit,
+            },
+            download: DownloadSection::from(&settings.download),
+            cache: CacheSection::from(&settings.cache),
+            image: ImageSection::new(settings.image_quality, &settings.animation),
+            video: VideoSection {
+                player: settings.player.command.clone(),
+            },
+            slideshow: SlideshowSection::from(&settings.slideshow),
+        }
+    }
+}
+
+impl From<&UiSettings> for UiSection {
+    fn from(ui: &UiSettings) -> Self {
+        Self {
+            tick_rate_ms: ui.tick_rate.as_millis() as u64,
+            fps: ui.fps,
+            popup_width: ui.popup_width,
+            popup_height: ui.popup_height,
+        }
+    }
+}
+
+impl From<&DownloadOptions> for DownloadSection {
+    fn from(download: &DownloadOptions) -> Self {
+        Self {
+            dir: download.dir.clone(),
+            filename_template: download.filename_template.source().to_string(),
+            metadata: download.metadata_mode.to_string(),
+            sidecars: join(&download.sidecar_formats),
+            embed_metadata: download.embed_metadata,
+        }
+    }
+}
+
+impl From<&CacheOptions> for CacheSection {
+    fn from(cache: &CacheOptions) -> Self {
+        Self {
+            dir: cache.dir.clone(),
+            size_mb: cache.max_bytes / (1024 * 1024),
+        }
+    }
+}
+
+impl ImageSection {
+    fn new(quality: ImageQuality, animation: &AnimationOptions) -> Self {
+        Self {
+            quality: quality.to_string(),
+            animation_memory_mb: animation.memory_cap_bytes / (1024 * 1024),
+        }
+    }
+}
+
+impl From<&SlideshowOptions> for SlideshowSection {
+    fn from(slideshow: &SlideshowOptions) -> Self {
+        Self {
+            interval: slideshow.interval.as_secs_f64(),
+            shuffle: slideshow.shuffle,
+        }
+    }
+}
+
+impl Default for UiSection {
+    fn default() -> Self {
+        Self::from(&UiSettings::default())
+    }
+}
+
+impl Default for SearchSection {
+    fn default() -> Self {
+        Self {
+            page_limit: DEFAULT_PAGE_LIMIT,
+        }
+    }
+}
+
+impl Default for DownloadSection {
+    fn default() -> Self {
+        Self::from(&DownloadOptions::default())
+    }
+}
+
+impl Default for CacheSection {
+    fn default() -> Self {
+        Self::from(&CacheOptions::default())
+    }
+}
+
+impl Default for ImageSection {
+    fn default() -> Self {
+        Self::new(ImageQuality::default(), &AnimationOptions::default())
+    }
+}
+
+impl Default for VideoSection {
+    fn default() -> Self {
+        Self {
+            player: PlayerOptions::default().command,
+        }
+    }
+}
+
+impl Default for SlideshowSection {
+    fn default() -> Self {
+        Self::from(&SlideshowOptions::default())
+    }
+}
+
+impl SettingsFile {
+    fn validate(self) -> Result<Settings> {
+        let ui = &self.ui;
+        if !(10..=1000).contains(&ui.tick_rate_ms) {
+            eyre::bail!(
+                "ui.tick_rate_ms must be between 10 and 1000, got {}",
+                ui.tick_rate_ms
+            );
+        }
+        if !(1..=MAX_FPS).contains(&ui.fps) {
+            eyre::bail!("ui.fps must be between 1 and {}, got {}", MAX_FPS, ui.fps);
+        }
+        for (key, percent) in [
+            ("ui.popup_width", ui.popup_width),
+            ("ui.popup_height", ui.popup_height),
+        ] {
+            if !(20..=100).contains(&percent) {
+                eyre::bail!("{} must be between 20 and 100, got {}", key, percent);
+            }
+        }
+
+        if !(1..=MAX_PAGE_LIMIT).contains(&self.search.page_limit) {
+            eyre::bail!(
+                "search.page_limit must be between 1 and {}, got {}",
+                MAX_PAGE_LIMIT,
+                self.search.page_limit
+            );
+        }
+
+        let download = self.download;
+        if download.dir.as_os_str().is_empty() {
+            eyre::bail!("download.dir must not be empty");
+        }
+        let filename_template = FilenameTemplate::parse(&download.filename_template)
+            .map_err(|e| eyre::eyre!("download.filename_template: {}", e))?;
+        let metadata_mode = download
+            .metadata
+            .parse()
+            .map_err(|e| eyre::eyre!("download.metadata: {}", e))?;
+        let sidecar_formats = SidecarFormat::parse_list(&download.sidecars)
+            .map_err(|e| eyre::eyre!("download.sidecars: {}", e))?;
+
+        let cache = self.cache.validate()?;
+
+        let image_quality = self
+            .image
+            .quality
+            .parse()
+            .map_err(|e| eyre::eyre!("image.quality: {}", e))?;
+        if self.image.animation_memory_mb == 0 {
+            eyre::bail!("image.animation_memory_mb must be greater than zero");
+        }
+
+        if self.video.player.split_whitespace().next().is_none() {
+            eyre::bail!("video.player must not be empty");
+        }
+
+        let interval = self.slideshow.interval;
+        if !interval.is_finite() || interval <= 0.0 {
+            eyre::bail!(
+                "slideshow.interval must be a positive number of seconds, got {}",
+                interval
+            );
+        }
+
+        Ok(Settings {
+            path: PathBuf::new(),
+            ui: UiSettings {
+                tick_rate: Duration::from_millis(ui.tick_rate_ms),
+                fps: ui.fps,
+                popup_width: ui.popup_width,
+                popup_height: ui.popup_height,
+            },
+            page_limit: self.search.page_limit,
+            download: DownloadOptions {
+                dir: download.dir,
+                filename_template,
+                metadata_mode,
+                sidecar_formats,
+                embed_metadata: download.embed_metadata,
+            },
+            cache,
+            image_quality,
+            animation: AnimationOptions::new(self.image.animation_memory_mb * 1024 * 1024),
+            player: PlayerOptions {
+                command: self.video.player,
+            },
+            slideshow: SlideshowOptions {
+                interval: Duration::from_secs_f64(interval),
+                shuffle: self.slideshow.shuffle,
+            },
+        })
+    }
+}
+
+impl CacheSection {
+    fn validate(self) -> Result<CacheOptions> {
+        if self.dir.as_os_str().is_empty() {
+            eyre::bail!("cache.dir must not be empty");
+        }
+        Ok(CacheOptions {
+            dir: self.dir,
+            max_bytes: self.size_mb * 1024 * 1024,
+        })
+    }
+}
+
+fn join(formats: &[SidecarFormat]) -> String {
+    formats
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let toml = toml::to_string(&SettingsFile::from(self)).map_err(|_| fmt::Error)?;
+        f.write_str(toml.trim_end())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::fs};
+
+    fn config_file(name: &str, contents: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("e6tu1-config-{}-{}.toml", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn load(name: &str, contents: &str) -> Result<Settings> {
+        let path = config_file(name, contents);
+        let settings = Settings::load_from(path.clone(), true);
+        let _ = fs::remove_file(path);
+        settings
+    }
+
+    #[test]
+    fn missing_file_uses_defaults() {
+        let path = std::env::temp_dir().join("e6tu1-config-does-not-exist.toml");
+        let settings = Settings::load_from(path, false).unwrap();
+        assert_eq!(settings.ui, UiSettings::default());
+        assert_eq!(settings.page_limit, DEFAULT_PAGE_LIMIT);
+        assert_eq!(settings.cache, CacheOptions::default());
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        for (contents, key) in [
+            ("[ui]\nfps = 0", "ui.fps"),
+            ("[ui]\ntick_rate_ms = 5", "ui.tick_rate_ms"),
+            ("[ui]\npopup_width = 101", "ui.popup_width"),
+            ("[search]\npage_limit = 321", "search.page_limit"),
+            ("[cache]\ndir = \"\"", "cache.dir"),
+            (
+                "[image]\nanimation_memory_mb = 0",
+                "image.animation_memory_mb",
+            ),
+            ("[video]\nplayer = \" \"", "video.player"),
+            ("[slideshow]\ninterval = -1.0", "slideshow.interval"),
+        ] {
+            let error = load("range", contents).unwrap_err().to_string();
+            assert!(error.contains(key), "{}: {}", key, error);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let error = load("unknown", "[ui]\nfsp = 30").unwrap_err().to_string();
+        assert!(error.contains("fsp"), "{}", error);
+        let error = load("section", "[uii]\nfps = 30").unwrap_err().to_string();
+        assert!(error.contains("uii"), "{}", error);
+    }
+
+    #[test]
+    fn env_overrides_map_to_known_keys() {
+        for (var, key) in ENV_OVERRIDES {
+            assert!(var.starts_with("E6TU1_"));
+            let result: Result<SettingsFile, _> = Config::builder()
+                .set_override(*key, "1")
+                .and_then(|builder| builder.build())
+                .and_then(Config::try_deserialize);
+            assert!(result.is_ok(), "{} -> {}: {:?}", var, key, result.err());
+        }
+
+        let file: SettingsFile = Config::builder()
+            .set_override("ui.fps", "60")
+            .and_then(|builder| builder.build())
+            .and_then(Config::try_deserialize)
+            .unwrap();
+        assert_eq!(file.ui.fps, 60);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let settings = load(
+            "display",
+            "[ui]\nfps = 60\n\
+             [download]\ndir = \"my \\\"downloads\\\"\"\n\
+             filename_template = \"{artist}/\\u001b{id}.{ext}\"\n\
+             sidecars = \"hydrus,tags\"\n\
+             [cache]\nsize_mb = 7\n\
+             [slideshow]\ninterval = 2.5\nshuffle = true\n",
+        )
+        .unwrap();
+        assert_eq!(
+            settings.download.filename_template.source(),
+            "{artist}/\u{1b}{id}.{ext}"
+        );
+
+        let shown = settings.to_string();
+        let reloaded = load("display-again", &shown).unwrap();
+        assert_eq!(reloaded.to_string(), shown);
+        assert_eq!(reloaded.ui, settings.ui);
+        assert_eq!(reloaded.download.dir, PathBuf::from("my \"downloads\""));
+        assert_eq!(
+            reloaded.download.filename_template.source(),
+            settings.download.filename_template.source()
+        );
+        assert_eq!(reloaded.cache, settings.cache);
+        assert_eq!(reloaded.slideshow.interval, Duration::from_millis(2500));
+    }
+
+    #[test]
+    fn cache_options_ignore_unrelated_mistakes() {
+        let read = |name, contents| {
+            let path = config_file(name, contents);
+            let options = Settings::load_cache_options_from(&path, true);
+            let _ = fs::remove_file(path);
+            options
+        };
+
+        let options = read(
+            "cache-lenient",
+            "[ui]\nfps = \"fast\"\nbogus = 1\n[cache]\ndir = \"somewhere\"\nsize_mb = 5\n",
+        )
+        .unwrap();
+        assert_eq!(
+            options,
+            CacheOptions {
+                dir: PathBuf::from("somewhere"),
+                max_bytes: 5 * 1024 * 1024,
+            }
+        );
+
+        assert_eq!(
+            read("cache-broken", "not toml [[").unwrap(),
+            CacheOptions::default()
+        );
+        assert_eq!(
+            read("cache-missing", "[ui]\nfps = 0\n").unwrap(),
+            CacheOptions::default()
+        );
+        assert!(read("cache-invalid", "[cache]\nsize = 5\n").is_err());
+    }
+}
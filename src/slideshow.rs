@@ -0,0 +1,122 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_SLIDESHOW_INTERVAL: Duration = Duration::from_secs(8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlideshowOptions {
+    pub interval: Duration,
+    pub shuffle: bool,
+}
+
+impl Default for SlideshowOptions {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_SLIDESHOW_INTERVAL,
+            shuffle: false,
+        }
+    }
+}
+
+pub struct Slideshow {
+    options: SlideshowOptions,
+    order: Vec<usize>,
+    position: usize,
+    paused: bool,
+    shown_since: Option<Instant>,
+    rng_state: u64,
+}
+
+impl Slideshow {
+    pub fn new(options: SlideshowOptions, result_count: usize, start: usize) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0x9E37_79B9_7F4A_7C15);
+
+        let mut slideshow = Self {
+            options,
+            order: Vec::new(),
+            position: 0,
+            paused: false,
+            shown_since: None,
+            rng_state: seed | 1,
+        };
+        slideshow.extend(result_count);
+
+        // Whatever is on screen stays the first slide; shuffling only affects what follows.
+        if let Some(i) = slideshow.order.iter().position(|&index| index == start) {
+            slideshow.order.swap(0, i);
+        }
+        slideshow
+    }
+
+    pub fn extend(&mut self, result_count: usize) {
+        let start = self.order.len();
+        self.order.extend(start..result_count);
+
+        if self.options.shuffle {
+            for i in (start + 1..self.order.len()).rev() {
+                let j = start + (self.next_random() % (i - start + 1) as u64) as usize;
+                self.order.swap(i, j);
+            }
+        }
+    }
+
+    fn next_random(&mut self) -> u64 {
+        // xorshift64; good enough for picking slide order.
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state = x;
+        x
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.order.get(self.position).copied()
+    }
+
+    pub fn peek_next(&self) -> Option<usize> {
+        self.order.get(self.position + 1).copied()
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.position + 1 >= self.order.len()
+    }
+
+    pub fn position(&self) -> (usize, usize) {
+        (self.position + 1, self.order.len())
+    }
+
+    pub fn skip(&mut self, offset: isize) -> Option<usize> {
+        if self.order.is_empty() {
+            return None;
+        }
+        let len = self.order.len() as isize;
+        self.position = (self.position as isize + offset).rem_euclid(len) as usize;
+        self.shown_since = None;
+        self.current()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.shown_since = None;
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.options.interval
+    }
+
+    pub fn is_due(&mut self, image_ready: bool, animation_done: bool) -> bool {
+        if self.paused || !image_ready {
+            return false;
+        }
+
+        let shown_since = *self.shown_since.get_or_insert_with(Instant::now);
+        shown_since.elapsed() >= self.options.interval && animation_done
+    }
+}
@@ -0,0 +1,160 @@
+use {
+    crate::{
+        anim::ImageProtocol,
+        api::SavedDownload,
+        app::DownloadProgress,
+        media::ImageVariant,
+        models::{E6Pool, E6Post},
+    },
+    color_eyre::eyre::Result,
+    image::DynamicImage,
+    std::{collections::HashMap, future::Future, sync::Arc},
+    tokio::{
+        sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+        task::AbortHandle,
+    },
+};
+
+/// At most one task of each kind runs at a time; spawning another replaces it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskKind {
+    Search,
+    FetchPost,
+    FetchPool,
+    RefreshPost,
+    LoadImage,
+    LoadZoomSource,
+    NextPage,
+    Download,
+    BulkDownload,
+}
+
+pub enum TaskMessage {
+    Searched(Result<Vec<E6Post>>),
+    PostFetched(Result<E6Post>),
+    PoolFetched(Result<(E6Pool, Vec<E6Post>)>),
+    PostRefreshed(Result<E6Post>),
+    ImageLoaded {
+        post_id: i64,
+        variant: ImageVariant,
+        result: Result<Box<ImageProtocol>>,
+    },
+    ZoomSourceLoaded {
+        post_id: i64,
+        result: Result<Arc<DynamicImage>>,
+    },
+    PageLoaded {
+        advance: bool,
+        result: Result<Vec<E6Post>>,
+    },
+    DownloadProgress(DownloadProgress),
+    Downloaded {
+        post: E6Post,
+        result: Result<SavedDownload>,
+    },
+    BulkPageListed(Result<Vec<E6Post>>),
+    BulkPostDownloaded {
+        post: E6Post,
+        result: Result<SavedDownload>,
+    },
+}
+
+struct Envelope {
+    kind: TaskKind,
+    generation: u64,
+    finished: bool,
+    message: TaskMessage,
+}
+
+pub struct Reporter {
+    kind: TaskKind,
+    generation: u64,
+    tx: UnboundedSender<Envelope>,
+}
+
+impl Reporter {
+    pub fn report(&self, message: TaskMessage) {
+        let _ = self.tx.send(Envelope {
+            kind: self.kind,
+            generation: self.generation,
+            finished: false,
+            message,
+        });
+    }
+}
+
+pub struct Tasks {
+    running: HashMap<TaskKind, (u64, AbortHandle)>,
+    next_generation: u64,
+    tx: UnboundedSender<Envelope>,
+    rx: UnboundedReceiver<Envelope>,
+}
+
+impl Tasks {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            running: HashMap::new(),
+            next_generation: 0,
+            tx,
+            rx,
+        }
+    }
+
+    pub fn spawn<F, Fut>(&mut self, kind: TaskKind, task: F)
+    where
+        F: FnOnce(Reporter) -> Fut,
+        Fut: Future<Output = TaskMessage> + Send + 'static,
+    {
+        self.cancel(kind);
+
+        let generation = self.next_generation;
+        self.next_generation += 1;
+
+        let reporter = Reporter {
+            kind,
+            generation,
+            tx: self.tx.clone(),
+        };
+        let tx = self.tx.clone();
+        let future = task(reporter);
+        let handle = tokio::spawn(async move {
+            let message = future.await;
+            let _ = tx.send(Envelope {
+                kind,
+                generation,
+                finished: true,
+                message,
+            });
+        });
+        self.running
+            .insert(kind, (generation, handle.abort_handle()));
+    }
+
+    pub fn cancel(&mut self, kind: TaskKind) {
+        if let Some((_, handle)) = self.running.remove(&kind) {
+            handle.abort();
+        }
+    }
+
+    pub fn is_running(&self, kind: TaskKind) -> bool {
+        self.running.contains_key(&kind)
+    }
+
+    pub fn try_recv(&mut self) -> Option<TaskMessage> {
+        while let Ok(envelope) = self.rx.try_recv() {
+            let current = self
+                .running
+                .get(&envelope.kind)
+                .is_some_and(|(generation, _)| *generation == envelope.generation);
+            if !current {
+                continue;
+            }
+            if envelope.finished {
+                self.running.remove(&envelope.kind);
+            }
+            return Some(envelope.message);
+        }
+        None
+    }
+}
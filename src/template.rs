@@ -0,0 +1,483 @@
+use {
+    crate::models::{E6Pool, E6Post},
+    color_eyre::eyre::{self, Result},
+    std::path::PathBuf,
+};
+
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{id} - {tags:3} - {md5}.{ext}";
+
+const MAX_COMPONENT_BYTES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Id,
+    Md5,
+    Ext,
+    Rating,
+    Artist,
+    Character,
+    Copyright,
+    Species,
+    Tags,
+    Pool,
+    PoolId,
+    Page,
+    Created,
+    Score,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "id" => Field::Id,
+            "md5" => Field::Md5,
+            "ext" => Field::Ext,
+            "rating" => Field::Rating,
+            "artist" | "artists" => Field::Artist,
+            "character" | "characters" => Field::Character,
+            "copyright" => Field::Copyright,
+            "species" => Field::Species,
+            "tags" => Field::Tags,
+            "pool" => Field::Pool,
+            "pool_id" => Field::PoolId,
+            "page" => Field::Page,
+            "created" | "date" => Field::Created,
+            "score" => Field::Score,
+            _ => return None,
+        })
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Field::Id | Field::PoolId | Field::Page | Field::Score)
+    }
+
+    fn is_list(self) -> bool {
+        matches!(
+            self,
+            Field::Artist | Field::Character | Field::Copyright | Field::Species | Field::Tags
+        )
+    }
+
+    fn needs_pool(self) -> bool {
+        matches!(self, Field::Pool | Field::PoolId | Field::Page)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Separator,
+    Placeholder {
+        field: Field,
+        width: Option<usize>,
+        zero_pad: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct PoolContext {
+    pub id: i64,
+    pub name: String,
+    pub page: usize,
+}
+
+impl PoolContext {
+    pub fn for_post(pool: &E6Pool, post: &E6Post) -> Option<Self> {
+        let index = pool.post_ids.iter().position(|&id| id == post.id)?;
+        Some(Self {
+            id: pool.id,
+            name: pool.name.replace('_', " "),
+            page: index + 1,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FilenameTemplate {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl Default for FilenameTemplate {
+    fn default() -> Self {
+        Self::parse(DEFAULT_FILENAME_TEMPLATE).expect("default filename template is valid")
+    }
+}
+
+impl FilenameTemplate {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => eyre::bail!(
+                                "Unclosed placeholder \"{{{}\" in template \"{}\"",
+                                placeholder,
+                                source
+                            ),
+                        }
+                    }
+
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Self::parse_placeholder(&placeholder, source)?);
+                }
+                '}' => eyre::bail!("Unmatched \"}}\" in template \"{}\"", source),
+                '/' | '\\' => {
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Separator);
+                }
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        if matches!(segments.last(), None | Some(Segment::Separator)) {
+            eyre::bail!("Template \"{}\" does not end in a file name", source);
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            segments,
+        })
+    }
+
+    fn parse_placeholder(placeholder: &str, source: &str) -> Result<Segment> {
+        let (name, spec) = match placeholder.split_once(':') {
+            Some((name, spec)) => (name.trim(), Some(spec.trim())),
+            None => (placeholder.trim(), None),
+        };
+
+        let field = Field::from_name(name).ok_or_else(|| {
+            eyre::eyre!(
+                "Unknown placeholder \"{{{}}}\" in template \"{}\"",
+                name,
+                source
+            )
+        })?;
+
+        let (width, zero_pad) = match spec {
+            None => (None, false),
+            Some(spec) => {
+                if !field.is_numeric() && !field.is_list() {
+                    eyre::bail!(
+                        "Placeholder \"{{{}}}\" does not take a format in template \"{}\"",
+                        name,
+                        source
+                    );
+                }
+
+                let width = spec.parse::<usize>().map_err(|_| {
+                    eyre::eyre!(
+                        "Invalid format \"{}\" for \"{{{}}}\" in template \"{}\"",
+                        spec,
+                        name,
+                        source
+                    )
+                })?;
+
+                (Some(width), spec.starts_with('0'))
+            }
+        };
+
+        Ok(Segment::Placeholder {
+            field,
+            width,
+            zero_pad,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn needs_pool(&self) -> bool {
+        self.segments.iter().any(
+            |segment| matches!(segment, Segment::Placeholder { field, .. } if field.needs_pool()),
+        )
+    }
+
+    pub fn render(&self, post: &E6Post, pool: Option<&PoolContext>) -> PathBuf {
+        let mut components = vec![String::new()];
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => components.last_mut().unwrap().push_str(text),
+                Segment::Separator => components.push(String::new()),
+                Segment::Placeholder {
+                    field,
+                    width,
+                    zero_pad,
+                } => {
+                    let value = field_value(*field, *width, *zero_pad, post, pool);
+                    components
+                        .last_mut()
+                        .unwrap()
+                        .push_str(&sanitize_value(&value));
+                }
+            }
+        }
+
+        let last = components.len() - 1;
+        components
+            .iter()
+            .enumerate()
+            .filter(|(_, component)| !component.is_empty())
+            .map(|(i, component)| sanitize_component(component, i == last))
+            .collect()
+    }
+}
+
+fn field_value(
+    field: Field,
+    width: Option<usize>,
+    zero_pad: bool,
+    post: &E6Post,
+    pool: Option<&PoolContext>,
+) -> String {
+    let number = |n: i64| match width {
+        Some(w) if zero_pad => format!("{:0w$}", n, w = w),
+        Some(w) => format!("{:w$}", n, w = w),
+        None => n.to_string(),
+    };
+
+    let list = |items: &[String], separator: &str, fallback: &str| {
+        let items: Vec<&str> = items
+            .iter()
+            .take(width.unwrap_or(usize::MAX))
+            .map(String::as_str)
+            .collect();
+        if items.is_empty() {
+            fallback.to_string()
+        } else {
+            items.join(separator)
+        }
+    };
+
+    match field {
+        Field::Id => number(post.id),
+        Field::Md5 => post.file.md5.clone(),
+        Field::Ext => post.file.ext.clone(),
+        Field::Rating => match post.rating.as_str() {
+            "s" => "safe".to_string(),
+            "q" => "questionable".to_string(),
+            "e" => "explicit".to_string(),
+            other => other.to_string(),
+        },
+        Field::Artist => list(&post.tags.artist, ", ", "unknown"),
+        Field::Character => list(&post.tags.character, ", ", "unknown"),
+        Field::Copyright => list(&post.tags.copyright, ", ", "unknown"),
+        Field::Species => list(&post.tags.species, ", ", "unknown"),
+        Field::Tags => {
+            let tags: Vec<String> = post
+                .tags
+                .general
+                .iter()
+                .chain(post.tags.artist.iter())
+                .chain(post.tags.character.iter())
+                .cloned()
+                .collect();
+            list(&tags, "_", "untagged")
+        }
+        Field::Pool => pool
+            .map(|p| p.name.clone())
+            .unwrap_or_else(|| "no pool".to_string()),
+        Field::PoolId => number(pool.map(|p| p.id).unwrap_or(0)),
+        Field::Page => number(pool.map(|p| p.page as i64).unwrap_or(0)),
+        Field::Created => post.created_at.chars().take(10).collect(),
+        Field::Score => number(post.score.total),
+    }
+}
+
+fn sanitize_value(value: &str) -> String {
+    value.replace(['/', '\\'], "_")
+}
+
+fn is_invalid_char(c: char) -> bool {
+    if c == '/' || c.is_control() {
+        return true;
+    }
+
+    cfg!(target_os = "windows") && matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*')
+}
+
+fn is_reserved_name(name: &str) -> bool {
+    if !cfg!(target_os = "windows") {
+        return false;
+    }
+
+    let stem = name.split('.').next().unwrap_or(name).to_ascii_uppercase();
+    matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.as_bytes()[3].is_ascii_digit())
+}
+
+fn sanitize_component(component: &str, is_file_name: bool) -> String {
+    let mut sanitized: String = component
+        .chars()
+        .map(|c| if is_invalid_char(c) { '_' } else { c })
+        .collect();
+
+    if cfg!(target_os = "windows") {
+        sanitized = sanitized.trim_end_matches(['.', ' ']).to_string();
+    }
+
+    if sanitized.is_empty() || sanitized == "." || sanitized == ".." {
+        sanitized = "_".to_string();
+    }
+
+    if is_reserved_name(&sanitized) {
+        sanitized.insert(0, '_');
+    }
+
+    truncate_component(&sanitized, is_file_name)
+}
+
+fn truncate_component(component: &str, is_file_name: bool) -> String {
+    if component.len() <= MAX_COMPONENT_BYTES {
+        return component.to_string();
+    }
+
+    let (stem, ext) = match component.rsplit_once('.') {
+        Some((stem, ext)) if is_file_name && ext.len() < 16 && !stem.is_empty() => {
+            (stem, Some(ext))
+        }
+        _ => (component, None),
+    };
+
+    let budget = MAX_COMPONENT_BYTES - ext.map(|e| e.len() + 1).unwrap_or(0);
+    let mut end = budget.min(stem.len());
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let stem = stem[..end].trim_end();
+    match ext {
+        Some(ext) => format!("{}.{}", stem, ext),
+        None => stem.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post() -> E6Post {
+        let mut post = E6Post {
+            id: 42,
+            created_at: "2024-05-06T07:08:09.000-04:00".to_string(),
+            rating: "e".to_string(),
+            ..E6Post::default()
+        };
+        post.file.md5 = "d41d8cd98f00b204e9800998ecf8427e".to_string();
+        post.file.ext = "png".to_string();
+        post.score.total = 7;
+        post.tags.general = vec!["solo".to_string(), "smile".to_string(), "hat".to_string()];
+        post.tags.artist = vec!["someone".to_string()];
+        post
+    }
+
+    fn render(template: &str, pool: Option<&PoolContext>) -> PathBuf {
+        FilenameTemplate::parse(template)
+            .unwrap()
+            .render(&post(), pool)
+    }
+
+    #[test]
+    fn renders_default_template() {
+        assert_eq!(
+            render(DEFAULT_FILENAME_TEMPLATE, None),
+            PathBuf::from("42 - solo_smile_hat - d41d8cd98f00b204e9800998ecf8427e.png")
+        );
+    }
+
+    #[test]
+    fn renders_fields_and_formats() {
+        assert_eq!(
+            render("{rating}/{date}/{id:06}_{score:3}.{ext}", None),
+            PathBuf::from("explicit/2024-05-06/000042_  7.png")
+        );
+        assert_eq!(
+            render("{artist} - {species}.{ext}", None),
+            PathBuf::from("someone - unknown.png")
+        );
+    }
+
+    #[test]
+    fn renders_pool_fields() {
+        let pool = PoolContext {
+            id: 9,
+            name: "My / Comic".to_string(),
+            page: 3,
+        };
+        assert_eq!(
+            render("{pool}/{page:03}.{ext}", Some(&pool)),
+            PathBuf::from("My _ Comic/003.png")
+        );
+        assert_eq!(
+            render("{pool_id}-{page}.{ext}", None),
+            PathBuf::from("0-0.png")
+        );
+        assert!(FilenameTemplate::parse("{pool}/{id}").unwrap().needs_pool());
+        assert!(!FilenameTemplate::default().needs_pool());
+    }
+
+    #[test]
+    fn escapes_braces_and_skips_empty_components() {
+        assert_eq!(render("{{{id}}}.{ext}", None), PathBuf::from("{42}.png"));
+        assert_eq!(render("a//{id}", None), PathBuf::from("a/42"));
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for template in [
+            "{id",
+            "id}",
+            "{nope}.png",
+            "{md5:3}.png",
+            "{id:x}.png",
+            "",
+            "{id}/",
+        ] {
+            assert!(
+                FilenameTemplate::parse(template).is_err(),
+                "{:?} should not parse",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn truncates_long_file_names_keeping_extension() {
+        let name = truncate_component(&format!("{}.png", "a".repeat(300)), true);
+        assert_eq!(name.len(), MAX_COMPONENT_BYTES);
+        assert!(name.ends_with("a.png"));
+
+        let name = truncate_component(&"é".repeat(150), false);
+        assert!(name.len() <= MAX_COMPONENT_BYTES);
+        assert!(name.chars().all(|c| c == 'é'));
+    }
+}
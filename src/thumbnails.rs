@@ -0,0 +1,115 @@
+use {
+    crate::{
+        api::E621Client,
+        media::{self, ImageVariant},
+        models::E6Post,
+    },
+    ratatui_image::{picker::Picker, protocol::StatefulProtocol},
+    std::collections::{HashMap, HashSet},
+    tokio::{
+        sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+        task::AbortHandle,
+    },
+};
+
+pub enum Thumbnail {
+    Ready(Box<StatefulProtocol>),
+    Failed,
+}
+
+pub struct Thumbnails {
+    client: E621Client,
+    picker: Picker,
+    loaded: HashMap<i64, Thumbnail>,
+    in_flight: HashMap<i64, AbortHandle>,
+    results_tx: UnboundedSender<(i64, Option<StatefulProtocol>)>,
+    results_rx: UnboundedReceiver<(i64, Option<StatefulProtocol>)>,
+}
+
+impl Thumbnails {
+    pub fn new(client: E621Client, picker: Picker) -> Self {
+        let (results_tx, results_rx) = mpsc::unbounded_channel();
+        Self {
+            client,
+            picker,
+            loaded: HashMap::new(),
+            in_flight: HashMap::new(),
+            results_tx,
+            results_rx,
+        }
+    }
+
+    pub fn request(&mut self, visible: &[E6Post], keep: &[E6Post]) {
+        let keep: HashSet<i64> = keep.iter().map(|post| post.id).collect();
+
+        self.in_flight.retain(|id, handle| {
+            let wanted = keep.contains(id);
+            if !wanted {
+                handle.abort();
+            }
+            wanted
+        });
+        self.loaded.retain(|id, _| keep.contains(id));
+
+        for post in visible {
+            if self.loaded.contains_key(&post.id) || self.in_flight.contains_key(&post.id) {
+                continue;
+            }
+
+            if media::variant_url(post, ImageVariant::Preview).is_none() {
+                self.loaded.insert(post.id, Thumbnail::Failed);
+                continue;
+            }
+
+            let id = post.id;
+            let post = post.clone();
+            let client = self.client.clone();
+            let picker = self.picker.clone();
+            let results_tx = self.results_tx.clone();
+
+            let handle = tokio::spawn(async move {
+                let protocol = match client.fetch_image(&post, ImageVariant::Preview).await {
+                    Ok(bytes) => tokio::task::spawn_blocking(move || {
+                        image::load_from_memory(&bytes)
+                            .ok()
+                            .map(|image| picker.new_resize_protocol(image))
+                    })
+                    .await
+                    .ok()
+                    .flatten(),
+                    Err(_) => None,
+                };
+                let _ = results_tx.send((id, protocol));
+            });
+            self.in_flight.insert(id, handle.abort_handle());
+        }
+    }
+
+    pub fn receive(&mut self) -> bool {
+        let mut received = false;
+        while let Ok((id, protocol)) = self.results_rx.try_recv() {
+            if self.in_flight.remove(&id).is_none() {
+                continue;
+            }
+            let thumbnail = match protocol {
+                Some(protocol) => Thumbnail::Ready(Box::new(protocol)),
+                None => Thumbnail::Failed,
+            };
+            self.loaded.insert(id, thumbnail);
+            received = true;
+        }
+        received
+    }
+
+    pub fn get_mut(&mut self, id: i64) -> Option<&mut Thumbnail> {
+        self.loaded.get_mut(&id)
+    }
+
+    pub fn clear(&mut self) {
+        for handle in self.in_flight.values() {
+            handle.abort();
+        }
+        self.in_flight.clear();
+        self.loaded.clear();
+    }
+}
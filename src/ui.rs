@@ -1,7 +1,12 @@
 use {
     crate::{
-        app::{App, AppState, InputMode},
-        widgets::{post_popup::E6PostPopup, post_viewer::PostViewer},
+        app::{App, AppState, InputMode, ResultsView},
+        bulk::BulkDownload,
+        media::MediaKind,
+        widgets::{
+            post_popup::E6PostPopup, post_summary::PostSummary, post_viewer::PostViewer,
+            thumbnail_grid::ThumbnailGrid,
+        },
     },
     ratatui::{
         Frame,
@@ -12,6 +17,10 @@ use {
     },
 };
 
+const SPLIT_MIN_WIDTH: u16 = 80;
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
 pub fn render(f: &mut Frame, app: &mut App) {
     let chunks = Layout::vertical([
         Constraint::Length(3),
@@ -33,6 +42,10 @@ pub fn render(f: &mut Frame, app: &mut App) {
         AppState::Error => render_error(f, app, chunks[2]),
     }
 
+    if let Some(ref bulk) = app.bulk_download {
+        render_bulk_progress(f, bulk, chunks[2]);
+    }
+
     render_help(f, app, chunks[3]);
 
     if let Some(ref progress) = app.download_progress {
@@ -78,6 +91,45 @@ fn render_progress_overlay(f: &mut Frame, progress: &crate::app::DownloadProgres
     f.render_widget(gauge, chunks[1]);
 }
 
+fn render_bulk_progress(f: &mut Frame, bulk: &BulkDownload, area: Rect) {
+    let bulk_area = Rect {
+        y: area.bottom().saturating_sub(3),
+        height: area.height.min(3),
+        ..area
+    };
+    f.render_widget(Clear, bulk_area);
+
+    let label = if bulk.enumerating {
+        format!(
+            "Listing posts from {}... {} found",
+            bulk.source.label(),
+            bulk.total_posts
+        )
+    } else {
+        format!(
+            "{} of {} posts | {:.2} MB / {:.2} MB | {} skipped, {} failed",
+            bulk.processed_posts,
+            bulk.total_posts,
+            bulk.downloaded_bytes as f64 / 1_048_576.0,
+            bulk.total_bytes as f64 / 1_048_576.0,
+            bulk.skipped_posts,
+            bulk.failed_posts
+        )
+    };
+
+    let gauge = Gauge::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Download All (Esc in results to cancel)")
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .gauge_style(Style::default().fg(Color::Green).bg(Color::Black))
+        .ratio(bulk.ratio().clamp(0.0, 1.0))
+        .label(label);
+    f.render_widget(gauge, bulk_area);
+}
+
 fn render_title(f: &mut Frame, area: Rect) {
     let title = Paragraph::new("E6TU1")
         .style(
@@ -156,15 +208,44 @@ fn render_input_screen(f: &mut Frame, app: &mut App, area: Rect) {
     if app.input_mode == InputMode::PostId {
         f.set_cursor_position((id_area.x + app.id_cursor_position as u16 + 1, id_area.y + 1));
     }
-}
 
-fn render_loading(f: &mut Frame, app: &App, area: Rect) {
-    let message = if let Some(ref progress) = app.download_progress {
-        progress.message.as_str()
+    let pool_area = Rect {
+        y: area.y + 9,
+        ..id_area
+    };
+
+    let pool_input_style = if app.input_mode == InputMode::PoolId {
+        Style::default().fg(Color::Yellow)
     } else {
-        "Loading..."
+        Style::default().fg(Color::DarkGray)
     };
 
+    let pool_input = Paragraph::new(app.pool_input.as_str())
+        .style(pool_input_style)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Or Enter Pool ID")
+                .border_style(if app.input_mode == InputMode::PoolId {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                }),
+        );
+    f.render_widget(pool_input, pool_area);
+
+    if app.input_mode == InputMode::PoolId {
+        f.set_cursor_position((
+            pool_area.x + app.pool_cursor_position as u16 + 1,
+            pool_area.y + 1,
+        ));
+    }
+}
+
+fn render_loading(f: &mut Frame, app: &App, area: Rect) {
+    let spinner = SPINNER[app.ticks as usize % SPINNER.len()];
+    let message = format!("{} {}", spinner, app.loading_message());
+
     let loading = Paragraph::new(message)
         .style(Style::default().fg(Color::Yellow))
         .block(Block::default().borders(Borders::ALL))
@@ -173,6 +254,27 @@ fn render_loading(f: &mut Frame, app: &App, area: Rect) {
 }
 
 fn render_search_results(f: &mut Frame, app: &mut App, area: Rect) {
+    let title = match app.current_pool {
+        Some(ref pool) => format!(
+            "Pool #{}: {} ({} posts)",
+            pool.id,
+            pool.name.replace('_', " "),
+            app.search_results.len()
+        ),
+        None => format!("Search Results ({} posts)", app.search_results.len()),
+    };
+
+    if app.results_view == ResultsView::Grid {
+        let grid = ThumbnailGrid::new(
+            &app.search_results,
+            app.list_state.selected(),
+            &app.download_index,
+            title,
+        );
+        f.render_stateful_widget(grid, area, &mut app.grid_state);
+        return;
+    }
+
     let items: Vec<ListItem> = app
         .search_results
         .iter()
@@ -184,7 +286,14 @@ fn render_search_results(f: &mut Frame, app: &mut App, area: Rect) {
                 _ => Color::White,
             };
 
+            let downloaded_marker = if app.download_index.contains(post) {
+                Span::styled("✔ ", Style::default().fg(Color::Green))
+            } else {
+                Span::raw("  ")
+            };
+
             let content = ratatui::text::Line::from(vec![
+                downloaded_marker,
                 Span::styled(format!("#{:<8}", post.id), Style::default().fg(Color::Cyan)),
                 Span::raw(" | "),
                 Span::styled(
@@ -212,11 +321,9 @@ fn render_search_results(f: &mut Frame, app: &mut App, area: Rect) {
         })
         .collect();
 
+    let list_block = Block::default().borders(Borders::ALL).title(title);
     let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title(format!(
-            "Search Results ({} posts)",
-            app.search_results.len()
-        )))
+        .block(list_block.clone())
         .highlight_style(
             Style::default()
                 .bg(Color::DarkGray)
@@ -224,12 +331,34 @@ fn render_search_results(f: &mut Frame, app: &mut App, area: Rect) {
         )
         .highlight_symbol(">> ");
 
-    f.render_stateful_widget(list, area, &mut app.list_state);
+    let selected_post = app
+        .list_state
+        .selected()
+        .and_then(|i| app.search_results.get(i));
+
+    match selected_post {
+        Some(post) if area.width >= SPLIT_MIN_WIDTH => {
+            let [list_area, preview_area] =
+                Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .areas(area);
+            app.list_area = list_block.inner(list_area);
+            f.render_stateful_widget(list, list_area, &mut app.list_state);
+            f.render_stateful_widget(
+                PostSummary::new(post),
+                preview_area,
+                &mut app.selection_preview,
+            );
+        }
+        _ => {
+            app.list_area = list_block.inner(area);
+            f.render_stateful_widget(list, area, &mut app.list_state);
+        }
+    }
 }
 
 fn render_post_view(f: &mut Frame, app: &mut App) {
     if let Some(ref post) = app.post {
-        let popup = E6PostPopup::new(post);
+        let popup = E6PostPopup::new(post).size(app.ui.popup_width, app.ui.popup_height);
         f.render_stateful_widget(popup, f.area(), &mut app.popup_state);
     }
 }
@@ -261,24 +390,84 @@ fn render_error(f: &mut Frame, app: &App, area: Rect) {
 }
 
 fn render_help(f: &mut Frame, app: &App, area: Rect) {
+    let media_kind = app.post.as_ref().map(MediaKind::of);
     let help_text = match app.state {
-        AppState::Input => "Enter: Submit | Tab: Switch Input | Esc: Clear | q: Quit",
-        AppState::Loading => "Loading...",
-        AppState::SearchResults => "↑↓: Navigate | Enter: View Post | q/Esc: Back",
-        AppState::Viewing => {
-            if app.popup_state.image_protocol.is_some() {
-                "↑↓: Scroll | d: Download | o: Open Browser | f: Full Image | q/Esc: Back"
-            } else {
-                "Loading image... | q/Esc: Back"
-            }
+        AppState::Input => {
+            "Enter: Submit | Tab/Shift-Tab: Switch Input | Ctrl-W: Delete Word | Esc: Clear | q: Quit"
         }
-        AppState::FullImageView => "f/q/Esc: Exit Full Screen",
+        AppState::Loading => "q/Esc: Cancel",
+        AppState::SearchResults => match (app.results_view, app.bulk_download.is_some()) {
+            (ResultsView::List, true) => {
+                "↑↓: Navigate | Enter: View Post | Esc: Cancel Download All"
+            }
+            (ResultsView::List, false) => {
+                "↑↓: Navigate | Enter: View Post | g: Grid | s: Slideshow | D: Download All | r: Refresh | q/Esc: Back"
+            }
+            (ResultsView::Grid, true) => {
+                "←↑↓→: Navigate | Enter: View Post | Esc: Cancel Download All"
+            }
+            (ResultsView::Grid, false) => {
+                "←↑↓→: Navigate | Enter: View Post | g: List | s: Slideshow | D: Download All | r: Refresh | q/Esc: Back"
+            }
+        },
+        AppState::Viewing => match app.popup_state.image_protocol {
+            _ if media_kind == Some(MediaKind::Video) => {
+                "↑↓: Scroll | p: Play Video | v: Variant | d: Download | o: Open Browser | f: Full Image | r: Refresh | q/Esc: Back"
+            }
+            Some(ref protocol) if protocol.playback_status().is_some() => {
+                "↑↓: Scroll | d: Download | o: Open Browser | f: Full Image | Space: Pause | ,/.: Step | [/]: Speed | r: Refresh | q/Esc: Back"
+            }
+            Some(_) => {
+                "↑↓: Scroll | d: Download | o: Open Browser | f: Full Image | u: Original | r: Refresh | q/Esc: Back"
+            }
+            None if media_kind == Some(MediaKind::Unsupported) => {
+                "↑↓: Scroll | d: Download | o: Open Browser | r: Refresh | q/Esc: Back"
+            }
+            None => "Loading image... | r: Refresh | q/Esc: Back",
+        },
+        AppState::FullImageView => match app.popup_state.image_protocol {
+            _ if media_kind == Some(MediaKind::Video) => {
+                "p: Play Video | v: Variant | f/q/Esc: Exit Full Screen"
+            }
+            Some(ref protocol) if protocol.playback_status().is_some() => {
+                "Space: Pause | ,/.: Step | [/]: Speed | f/q/Esc: Exit Full Screen"
+            }
+            Some(_) => {
+                "+/-: Zoom | 0: Fit | ←↓↑→/hjkl: Pan | u: Original | f/q/Esc: Exit Full Screen"
+            }
+            None => "f/q/Esc: Exit Full Screen",
+        },
         AppState::Error => "Press any key to continue",
     };
 
-    let help = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::DarkGray))
-        .centered();
+    let browsing_results = matches!(app.state, AppState::Viewing | AppState::FullImageView)
+        && !app.search_results.is_empty();
+    let help_text = match app.slideshow {
+        Some(ref slideshow) if app.state == AppState::FullImageView => {
+            let (position, total) = slideshow.position();
+            format!(
+                "Slideshow {}/{} every {}s | Space: {} | n/b: Skip | s: Stop | f/q/Esc: Exit",
+                position,
+                total,
+                slideshow.interval().as_secs_f64(),
+                if slideshow.is_paused() {
+                    "Resume"
+                } else {
+                    "Pause"
+                }
+            )
+        }
+        _ if browsing_results => format!("n/b: Next/Prev | s: Slideshow | {}", help_text),
+        _ => help_text.to_string(),
+    };
+
+    let help = match app.notice {
+        Some(ref notice) => {
+            Paragraph::new(notice.as_str()).style(Style::default().fg(Color::Yellow))
+        }
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+    }
+    .centered();
     f.render_widget(help, area);
 }
 
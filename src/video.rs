@@ -0,0 +1,113 @@
+use {
+    crate::models::E6Post,
+    color_eyre::eyre::{self, Result},
+    std::process::{Command, Stdio},
+};
+
+pub const DEFAULT_PLAYER_COMMAND: &str = "mpv {url}";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerOptions {
+    pub command: String,
+}
+
+impl Default for PlayerOptions {
+    fn default() -> Self {
+        Self {
+            command: DEFAULT_PLAYER_COMMAND.to_string(),
+        }
+    }
+}
+
+impl PlayerOptions {
+    /// `{url}` is replaced by the variant URL, or appended when the command has no placeholder.
+    pub fn launch(&self, url: &str) -> Result<()> {
+        let mut parts = self.command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| eyre::eyre!("No video player command configured"))?;
+
+        let mut args: Vec<String> = parts.map(|arg| arg.replace("{url}", url)).collect();
+        if !self.command.contains("{url}") {
+            args.push(url.to_string());
+        }
+
+        Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| eyre::eyre!("Failed to launch \"{}\": {}", program, e))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoVariant {
+    pub label: String,
+    pub codec: String,
+    pub width: i64,
+    pub height: i64,
+    pub size: i64,
+    pub url: String,
+}
+
+pub fn variants(post: &E6Post) -> Vec<VideoVariant> {
+    let alternates = &post.sample.alternates;
+    let mut variants = Vec::new();
+
+    if let Some(ref url) = post.file.url {
+        let codec = alternates
+            .original
+            .as_ref()
+            .map(|original| original.codec.clone())
+            .unwrap_or_default();
+        variants.push(VideoVariant {
+            label: format!("original ({})", post.file.ext),
+            codec,
+            width: post.file.width,
+            height: post.file.height,
+            size: post.file.size,
+            url: url.clone(),
+        });
+    }
+
+    if let Some(mp4) = alternates.variants.as_ref().and_then(|v| v.mp4.as_ref())
+        && let Some(ref url) = mp4.url
+    {
+        variants.push(VideoVariant {
+            label: "mp4".to_string(),
+            codec: mp4.codec.clone(),
+            width: mp4.width,
+            height: mp4.height,
+            size: mp4.size,
+            url: url.clone(),
+        });
+    }
+
+    let mut samples: Vec<VideoVariant> = alternates
+        .samples
+        .iter()
+        .flat_map(|samples| &samples.0)
+        .filter_map(|(name, quality)| {
+            Some(VideoVariant {
+                label: name.clone(),
+                codec: quality.codec.clone(),
+                width: quality.width,
+                height: quality.height,
+                size: quality.size,
+                url: quality.url.clone()?,
+            })
+        })
+        .collect();
+    samples.sort_by(|a, b| b.height.cmp(&a.height).then(a.label.cmp(&b.label)));
+    variants.extend(samples);
+
+    variants
+}
+
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    format!("{}:{:02}", total / 60, total % 60)
+}
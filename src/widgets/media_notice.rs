@@ -0,0 +1,65 @@
+use {
+    crate::{
+        anim::ImageProtocol,
+        media::{self, MediaKind},
+        models::E6Post,
+    },
+    ratatui::{
+        buffer::Buffer,
+        layout::{Constraint, Layout, Rect},
+        style::{Color, Modifier, Style},
+        text::{Line, Span},
+        widgets::{Block, Borders, Paragraph, Widget, Wrap},
+    },
+};
+
+pub fn render_notice(post: &E6Post, area: Rect, buf: &mut Buffer) -> Rect {
+    if MediaKind::of(post) != MediaKind::Unsupported || area.height < 8 {
+        return area;
+    }
+
+    let [notice_area, rest] =
+        Layout::vertical([Constraint::Length(4), Constraint::Min(0)]).areas(area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red));
+    let format = if post.file.ext.is_empty() {
+        "unknown".to_string()
+    } else {
+        format!(".{}", post.file.ext)
+    };
+    let label = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+    let lines = if area.width < 36 {
+        vec![
+            Line::from(Span::styled("Format not previewable", label)),
+            Line::from(Span::styled(format!("({})", format), label)),
+        ]
+    } else {
+        vec![
+            Line::from(Span::styled(
+                format!("Format not previewable ({})", format),
+                label,
+            )),
+            Line::from(Span::styled(
+                "d: Download | o: Open Browser",
+                Style::default().fg(Color::DarkGray),
+            )),
+        ]
+    };
+    let notice = Paragraph::new(lines)
+        .block(block)
+        .centered()
+        .wrap(Wrap { trim: true });
+    notice.render(notice_area, buf);
+
+    rest
+}
+
+pub fn placeholder_message(post: &E6Post, protocol: Option<&ImageProtocol>) -> String {
+    match protocol.and_then(|p| p.error()) {
+        Some(error) => format!("Failed to decode image: {}", error),
+        None if media::available_variants(post).is_empty() => "No preview available".to_string(),
+        None => "Loading image...".to_string(),
+    }
+}
@@ -0,0 +1,76 @@
+use {
+    crate::zoom::{Crop, ZoomLevel},
+    ratatui::{
+        buffer::Buffer,
+        layout::Rect,
+        style::{Color, Style},
+        widgets::{Block, Borders, Widget},
+    },
+};
+
+pub struct Minimap {
+    image_size: (u32, u32),
+    crop: Crop,
+    level: ZoomLevel,
+}
+
+impl Minimap {
+    pub fn new(image_size: (u32, u32), crop: Crop, level: ZoomLevel) -> Self {
+        Self {
+            image_size,
+            crop,
+            level,
+        }
+    }
+
+    /// Assumes cells are roughly twice as tall as they are wide.
+    pub fn width_for(image_size: (u32, u32), height: u16) -> u16 {
+        let (width, image_height) = image_size;
+        let inner_height = height.saturating_sub(2) as f64;
+        let inner_width = inner_height * 2.0 * width as f64 / image_height.max(1) as f64;
+        (inner_width.round() as u16).clamp(4, 48) + 2
+    }
+}
+
+impl Widget for Minimap {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::DarkGray))
+            .title(self.level.label());
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        if inner.width == 0 || inner.height == 0 {
+            return;
+        }
+
+        let (width, height) = self.image_size;
+        let to_cells = |pixels: u32, total: u32, cells: u16| {
+            (pixels as f64 / total.max(1) as f64 * cells as f64) as u16
+        };
+
+        let left = to_cells(self.crop.x, width, inner.width);
+        let top = to_cells(self.crop.y, height, inner.height);
+        let right = to_cells(self.crop.x + self.crop.width, width, inner.width)
+            .max(left + 1)
+            .min(inner.width);
+        let bottom = to_cells(self.crop.y + self.crop.height, height, inner.height)
+            .max(top + 1)
+            .min(inner.height);
+
+        for y in 0..inner.height {
+            for x in 0..inner.width {
+                let visible = (left..right).contains(&x) && (top..bottom).contains(&y);
+                let (symbol, style) = if visible {
+                    ("█", Style::default().fg(Color::Cyan))
+                } else {
+                    ("·", Style::default().fg(Color::DarkGray))
+                };
+                buf[(inner.x + x, inner.y + y)]
+                    .set_symbol(symbol)
+                    .set_style(style);
+            }
+        }
+    }
+}
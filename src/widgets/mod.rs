@@ -1,2 +1,7 @@
+pub mod media_notice;
+pub mod minimap;
+pub mod playback_bar;
 pub mod post_popup;
+pub mod post_summary;
 pub mod post_viewer;
+pub mod thumbnail_grid;
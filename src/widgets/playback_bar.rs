@@ -0,0 +1,55 @@
+use {
+    crate::anim::PlaybackStatus,
+    ratatui::{
+        buffer::Buffer,
+        layout::Rect,
+        style::{Color, Style},
+        text::{Line, Span},
+        widgets::Widget,
+    },
+};
+
+pub struct PlaybackBar {
+    status: PlaybackStatus,
+}
+
+impl PlaybackBar {
+    pub fn new(status: PlaybackStatus) -> Self {
+        Self { status }
+    }
+}
+
+impl Widget for PlaybackBar {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let status = self.status;
+        let icon = if status.paused { "⏸" } else { "▶" };
+        let counter = match status.frame_count {
+            Some(count) => format!(" {} {}/{} ", icon, status.frame + 1, count),
+            None => format!(" {} {}/? ", icon, status.frame + 1),
+        };
+        let speed = format!(" {}x ", status.speed);
+
+        let track_width =
+            (area.width as usize).saturating_sub(counter.chars().count() + speed.chars().count());
+        let position = match status.frame_count {
+            Some(count) if count > 1 => status.frame * track_width.saturating_sub(1) / (count - 1),
+            _ => 0,
+        };
+
+        let mut spans = vec![Span::styled(counter, Style::default().fg(Color::Yellow))];
+        if track_width > 0 {
+            spans.push(Span::styled(
+                "━".repeat(position),
+                Style::default().fg(Color::Cyan),
+            ));
+            spans.push(Span::styled("●", Style::default().fg(Color::White)));
+            spans.push(Span::styled(
+                "─".repeat(track_width - position - 1),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        spans.push(Span::styled(speed, Style::default().fg(Color::Yellow)));
+
+        Line::from(spans).render(area, buf);
+    }
+}
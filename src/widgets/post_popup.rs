@@ -1,18 +1,44 @@
 use {
-    crate::{anim::ImageProtocol, models::E6Post},
+    super::{media_notice, playback_bar::PlaybackBar},
+    crate::{
+        anim::ImageProtocol,
+        media::{ImageVariant, MediaKind},
+        models::E6Post,
+        settings::DEFAULT_POPUP_SIZE,
+        video,
+    },
     ratatui::{
         buffer::Buffer,
-        layout::{Constraint, Flex, Layout, Rect},
+        layout::{Constraint, Flex, Layout, Position, Rect},
         style::{Color, Modifier, Style},
         text::{Line, Span, Text},
         widgets::{Block, Borders, Clear, Paragraph, StatefulWidget, Widget, Wrap},
     },
     ratatui_image::{Resize, StatefulImage},
+    std::collections::HashMap,
 };
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PopupLink {
+    Tag(String),
+    Source(String),
+}
+
+type SpanLinks = HashMap<(usize, usize), PopupLink>;
+
+type PlacedLink = (usize, u16, u16, PopupLink);
+
 pub struct E6PostPopupState {
     pub image_protocol: Option<ImageProtocol>,
     pub scroll_offset: u16,
+    pub selected_variant: usize,
+    pub image_variant: Option<ImageVariant>,
+    pub original_requested: bool,
+    pub load_failed: bool,
+    pub area: Rect,
+    pub info_area: Rect,
+    pub links: Vec<(Rect, PopupLink)>,
+    max_scroll: u16,
 }
 
 impl E6PostPopupState {
@@ -20,13 +46,34 @@ impl E6PostPopupState {
         Self {
             image_protocol: None,
             scroll_offset: 0,
+            selected_variant: 0,
+            image_variant: None,
+            original_requested: false,
+            load_failed: false,
+            area: Rect::default(),
+            info_area: Rect::default(),
+            links: Vec::new(),
+            max_scroll: 0,
         }
     }
+
+    pub fn scroll(&mut self, lines: i32) {
+        self.scroll_offset =
+            (self.scroll_offset as i32 + lines).clamp(0, self.max_scroll as i32) as u16;
+    }
+
+    pub fn link_at(&self, position: Position) -> Option<&PopupLink> {
+        self.links
+            .iter()
+            .find(|(area, _)| area.contains(position))
+            .map(|(_, link)| link)
+    }
 }
 
 pub struct E6PostPopup<'a> {
     post: &'a E6Post,
     title: String,
+    size: (u16, u16),
 }
 
 impl<'a> E6PostPopup<'a> {
@@ -34,9 +81,15 @@ impl<'a> E6PostPopup<'a> {
         Self {
             post,
             title: format!("Post #{}", post.id),
+            size: DEFAULT_POPUP_SIZE,
         }
     }
 
+    pub fn size(mut self, percent_x: u16, percent_y: u16) -> Self {
+        self.size = (percent_x, percent_y);
+        self
+    }
+
     fn popup_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
         let vertical = Layout::vertical([Constraint::Percentage(percent_y)]).flex(Flex::Center);
         let horizontal = Layout::horizontal([Constraint::Percentage(percent_x)]).flex(Flex::Center);
@@ -45,8 +98,24 @@ impl<'a> E6PostPopup<'a> {
         area
     }
 
-    fn build_info_text(&self) -> Text<'a> {
+    fn tag_line(tags: &'a [String], line: usize, links: &mut SpanLinks) -> Line<'a> {
+        let mut spans = Vec::new();
+        for (i, tag) in tags.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::raw(", "));
+            }
+            links.insert((line, spans.len()), PopupLink::Tag(tag.clone()));
+            spans.push(Span::styled(
+                tag.as_str(),
+                Style::default().add_modifier(Modifier::UNDERLINED),
+            ));
+        }
+        Line::from(spans)
+    }
+
+    fn build_info_text(&self, selected_variant: usize) -> (Vec<Line<'a>>, SpanLinks) {
         let mut lines = Vec::new();
+        let mut links = SpanLinks::new();
 
         lines.push(Line::from(vec![
             Span::styled(
@@ -127,6 +196,43 @@ impl<'a> E6PostPopup<'a> {
             )),
         ]));
 
+        if MediaKind::of(self.post) == MediaKind::Video {
+            lines.push(Line::raw(""));
+            lines.push(Line::from(Span::styled(
+                "Video:",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )));
+            if let Some(duration) = self.post.duration {
+                lines.push(Line::raw(format!(
+                    "Duration: {}",
+                    video::format_duration(duration)
+                )));
+            }
+            for (i, variant) in video::variants(self.post).iter().enumerate() {
+                let marker = if i == selected_variant { "▶ " } else { "  " };
+                let codec = if variant.codec.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {}", variant.codec)
+                };
+                let line = format!(
+                    "{}{}: {}x{}{}, {:.2} MB",
+                    marker,
+                    variant.label,
+                    variant.width,
+                    variant.height,
+                    codec,
+                    variant.size as f64 / 1_048_576.0
+                );
+                let style = if i == selected_variant {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                lines.push(Line::from(Span::styled(line, style)));
+            }
+        }
+
         if !self.post.tags.artist.is_empty() {
             lines.push(Line::raw(""));
             lines.push(Line::from(Span::styled(
@@ -135,7 +241,11 @@ impl<'a> E6PostPopup<'a> {
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD),
             )));
-            lines.push(Line::from(Span::raw(self.post.tags.artist.join(", "))));
+            lines.push(Self::tag_line(
+                &self.post.tags.artist,
+                lines.len(),
+                &mut links,
+            ));
         }
 
         if !self.post.tags.character.is_empty() {
@@ -146,7 +256,11 @@ impl<'a> E6PostPopup<'a> {
                     .fg(Color::Green)
                     .add_modifier(Modifier::BOLD),
             )));
-            lines.push(Line::from(Span::raw(self.post.tags.character.join(", "))));
+            lines.push(Self::tag_line(
+                &self.post.tags.character,
+                lines.len(),
+                &mut links,
+            ));
         }
 
         if !self.post.tags.species.is_empty() {
@@ -157,7 +271,11 @@ impl<'a> E6PostPopup<'a> {
                     .fg(Color::Magenta)
                     .add_modifier(Modifier::BOLD),
             )));
-            lines.push(Line::from(Span::raw(self.post.tags.species.join(", "))));
+            lines.push(Self::tag_line(
+                &self.post.tags.species,
+                lines.len(),
+                &mut links,
+            ));
         }
 
         if !self.post.description.is_empty() {
@@ -184,24 +302,96 @@ impl<'a> E6PostPopup<'a> {
                     .add_modifier(Modifier::BOLD),
             )));
             for source in &self.post.sources {
+                links.insert((lines.len(), 0), PopupLink::Source(source.clone()));
                 lines.push(Line::from(Span::styled(
                     source,
-                    Style::default().fg(Color::Blue),
+                    Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::UNDERLINED),
                 )));
             }
         }
 
-        Text::from(lines)
+        (lines, links)
     }
 }
 
+fn text_width(text: &str) -> u16 {
+    Span::raw(text).width() as u16
+}
+
+/// Wrapped here rather than by `Paragraph` so links can be mapped to screen cells.
+fn wrap_lines(
+    lines: Vec<Line<'_>>,
+    links: &SpanLinks,
+    width: u16,
+) -> (Vec<Line<'static>>, Vec<PlacedLink>) {
+    let mut rows = Vec::new();
+    let mut placed = Vec::new();
+    if width == 0 {
+        return (rows, placed);
+    }
+
+    for (line_index, line) in lines.into_iter().enumerate() {
+        let mut row: Vec<Span<'static>> = Vec::new();
+        let mut x = 0;
+        let mut wrapped = false;
+
+        for (span_index, span) in line.spans.iter().enumerate() {
+            let link = links.get(&(line_index, span_index));
+
+            for token in span.content.split_inclusive(' ') {
+                let word_width = text_width(token.trim_end());
+                if x > 0 && x + word_width > width {
+                    rows.push(Line::from(std::mem::take(&mut row)));
+                    x = 0;
+                    wrapped = true;
+                }
+                // Like `Wrap { trim: true }`, wrapped rows don't start with spaces.
+                if x == 0 && wrapped && token.trim().is_empty() {
+                    continue;
+                }
+
+                let mut piece = String::new();
+                let mut piece_x = x;
+                for c in token.chars() {
+                    let char_width = text_width(c.encode_utf8(&mut [0; 4]));
+                    if c != ' ' && x + char_width > width && x > 0 {
+                        if let Some(link) = link {
+                            placed.push((rows.len(), piece_x, x - piece_x, link.clone()));
+                        }
+                        row.push(Span::styled(std::mem::take(&mut piece), span.style));
+                        rows.push(Line::from(std::mem::take(&mut row)));
+                        x = 0;
+                        piece_x = 0;
+                        wrapped = true;
+                    }
+                    piece.push(c);
+                    x += char_width;
+                }
+
+                if let Some(link) = link {
+                    let linked_width = text_width(piece.trim_end());
+                    placed.push((rows.len(), piece_x, linked_width, link.clone()));
+                }
+                row.push(Span::styled(piece, span.style));
+            }
+        }
+
+        rows.push(Line::from(row));
+    }
+
+    (rows, placed)
+}
+
 impl<'a> StatefulWidget for E6PostPopup<'a> {
     type State = E6PostPopupState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
         Clear.render(area, buf);
 
-        let popup_area = Self::popup_area(area, 80, 85);
+        let popup_area = Self::popup_area(area, self.size.0, self.size.1);
+        state.area = popup_area;
 
         let outer_block = Block::default()
             .borders(Borders::ALL)
@@ -220,31 +410,54 @@ impl<'a> StatefulWidget for E6PostPopup<'a> {
         let horizontal =
             Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]);
         let [image_area, info_area] = horizontal.areas(inner_area);
+        let image_area = media_notice::render_notice(self.post, image_area, buf);
+        let preview_title = match state.image_variant {
+            Some(variant) => format!("Preview ({})", variant.label()),
+            None => "Preview".to_string(),
+        };
 
         if let Some(ref mut image_protocol) = state.image_protocol {
             image_protocol.try_advance();
+        }
 
+        if let Some(ref mut image_protocol) = state.image_protocol
+            && image_protocol.is_ready()
+        {
             let image_block = Block::default()
                 .borders(Borders::ALL)
-                .title("Preview")
+                .title(preview_title.clone())
                 .border_style(Style::default().fg(Color::Cyan));
 
-            let image_inner = image_block.inner(image_area);
+            let mut image_inner = image_block.inner(image_area);
             image_block.render(image_area, buf);
 
-            let protocol = image_protocol.current_protocol_mut();
-            let image_widget = StatefulImage::new().resize(Resize::Fit(None));
-            StatefulWidget::render(image_widget, image_inner, buf, protocol);
+            if let Some(status) = image_protocol.playback_status()
+                && image_inner.height > 1
+            {
+                let [picture_area, bar_area] =
+                    Layout::vertical([Constraint::Min(1), Constraint::Length(1)])
+                        .areas(image_inner);
+                PlaybackBar::new(status).render(bar_area, buf);
+                image_inner = picture_area;
+            }
+
+            image_protocol.set_area(image_inner);
+            if let Some(protocol) = image_protocol.current_protocol_mut() {
+                let image_widget = StatefulImage::new().resize(Resize::Fit(None));
+                StatefulWidget::render(image_widget, image_inner, buf, protocol);
+            }
         } else {
             let placeholder_block = Block::default()
                 .borders(Borders::ALL)
-                .title("Preview")
+                .title(preview_title.clone())
                 .border_style(Style::default().fg(Color::DarkGray));
 
             placeholder_block.clone().render(image_area, buf);
 
             let placeholder_inner = placeholder_block.inner(image_area);
-            let placeholder_text = Paragraph::new("Loading image...")
+            let message =
+                media_notice::placeholder_message(self.post, state.image_protocol.as_ref());
+            let placeholder_text = Paragraph::new(message)
                 .style(Style::default().fg(Color::DarkGray))
                 .centered()
                 .wrap(Wrap { trim: true });
@@ -260,17 +473,33 @@ impl<'a> StatefulWidget for E6PostPopup<'a> {
         let info_inner = info_block.inner(info_area);
         info_block.render(info_area, buf);
 
-        let info_text = self.build_info_text();
-        let info_paragraph = Paragraph::new(info_text.clone())
+        let (info_lines, span_links) = self.build_info_text(state.selected_variant);
+        let (rows, placed_links) = wrap_lines(info_lines, &span_links, info_inner.width);
+
+        let max_scroll = rows.len().saturating_sub(info_inner.height as usize);
+        state.max_scroll = max_scroll.min(u16::MAX as usize) as u16;
+        state.scroll_offset = state.scroll_offset.min(state.max_scroll);
+        state.info_area = info_inner;
+        state.links = placed_links
+            .into_iter()
+            .filter_map(|(row, x, width, link)| {
+                let row = row.checked_sub(state.scroll_offset as usize)?;
+                (row < info_inner.height as usize).then(|| {
+                    let area = Rect {
+                        x: info_inner.x + x,
+                        y: info_inner.y + row as u16,
+                        width,
+                        height: 1,
+                    };
+                    (area.intersection(info_inner), link)
+                })
+            })
+            .collect();
+
+        Paragraph::new(Text::from(rows))
             .scroll((state.scroll_offset, 0))
-            .wrap(Wrap { trim: true });
-
-        info_paragraph.render(info_inner, buf);
+            .render(info_inner, buf);
 
-        let max_scroll = info_text
-            .lines
-            .len()
-            .saturating_sub(info_inner.height as usize);
         if max_scroll > 0 {
             let scroll_indicator = format!(" {}/{} ", state.scroll_offset, max_scroll);
             let indicator_len = scroll_indicator.len() as u16;
@@ -288,3 +517,87 @@ impl<'a> StatefulWidget for E6PostPopup<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap(lines: Vec<Line<'_>>, links: &SpanLinks, width: u16) -> (Vec<String>, Vec<PlacedLink>) {
+        let (rows, placed) = wrap_lines(lines, links, width);
+        let rows = rows
+            .iter()
+            .map(|row| row.spans.iter().map(|span| span.content.as_ref()).collect())
+            .collect();
+        (rows, placed)
+    }
+
+    fn tag(name: &str) -> PopupLink {
+        PopupLink::Tag(name.to_string())
+    }
+
+    #[test]
+    fn keeps_short_lines_intact() {
+        let lines = vec![Line::from("ID: 42"), Line::from("")];
+        let (rows, placed) = wrap(lines, &SpanLinks::new(), 20);
+        assert_eq!(rows, ["ID: 42", ""]);
+        assert!(placed.is_empty());
+    }
+
+    #[test]
+    fn wraps_at_spaces_without_leading_whitespace() {
+        let lines = vec![Line::from("one two three")];
+        let (rows, _) = wrap(lines, &SpanLinks::new(), 8);
+        assert_eq!(rows, ["one two ", "three"]);
+    }
+
+    #[test]
+    fn places_tag_links_around_separators() {
+        let tags = ["wolf", "solo", "smile"].map(String::from);
+        let mut links = SpanLinks::new();
+        let line = E6PostPopup::tag_line(&tags, 0, &mut links);
+
+        let (rows, placed) = wrap(vec![line], &links, 12);
+        assert_eq!(rows, ["wolf, solo, ", "smile"]);
+        assert_eq!(
+            placed,
+            [
+                (0, 0, 4, tag("wolf")),
+                (0, 6, 4, tag("solo")),
+                (1, 0, 5, tag("smile")),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_words_wider_than_the_pane() {
+        let tags = ["a", "very_long_tag_name"].map(String::from);
+        let mut links = SpanLinks::new();
+        let line = E6PostPopup::tag_line(&tags, 1, &mut links);
+
+        let (rows, placed) = wrap(vec![Line::from("Tags:"), line], &links, 8);
+        assert_eq!(rows, ["Tags:", "a, ", "very_lon", "g_tag_na", "me"]);
+        assert_eq!(
+            placed,
+            [
+                (1, 0, 1, tag("a")),
+                (2, 0, 8, tag("very_long_tag_name")),
+                (3, 0, 8, tag("very_long_tag_name")),
+                (4, 0, 2, tag("very_long_tag_name")),
+            ]
+        );
+    }
+
+    #[test]
+    fn measures_wide_characters_in_columns() {
+        let lines = vec![Line::from("日本語テキスト")];
+        let (rows, _) = wrap(lines, &SpanLinks::new(), 6);
+        assert_eq!(rows, ["日本語", "テキス", "ト"]);
+    }
+
+    #[test]
+    fn zero_width_produces_nothing() {
+        let (rows, placed) = wrap(vec![Line::from("text")], &SpanLinks::new(), 0);
+        assert!(rows.is_empty());
+        assert!(placed.is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+use {
+    super::{media_notice, playback_bar::PlaybackBar},
+    crate::{
+        models::E6Post,
+        preview::{PreviewImage, SelectionPreview},
+    },
+    ratatui::{
+        buffer::Buffer,
+        layout::{Constraint, Layout, Rect},
+        style::{Color, Modifier, Style},
+        text::{Line, Span},
+        widgets::{Block, Borders, Paragraph, StatefulWidget, Widget, Wrap},
+    },
+    ratatui_image::{Resize, StatefulImage},
+};
+
+pub struct PostSummary<'a> {
+    post: &'a E6Post,
+}
+
+impl<'a> PostSummary<'a> {
+    pub fn new(post: &'a E6Post) -> Self {
+        Self { post }
+    }
+
+    fn info_lines(&self) -> Vec<Line<'a>> {
+        let label = |text: &'static str| {
+            Span::styled(
+                text,
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+        };
+        let joined = |tags: &[String]| {
+            if tags.is_empty() {
+                "unknown".to_string()
+            } else {
+                tags.join(", ")
+            }
+        };
+
+        let post = self.post;
+        vec![
+            Line::from(vec![
+                label("Rating: "),
+                Span::raw(post.rating.to_uppercase()),
+                label("  Score: "),
+                Span::raw(post.score.total.to_string()),
+                label("  Favorites: "),
+                Span::raw(post.fav_count.to_string()),
+            ]),
+            Line::from(vec![
+                label("File: "),
+                Span::raw(format!(
+                    "{}x{} {}, {:.2} MB",
+                    post.file.width,
+                    post.file.height,
+                    post.file.ext,
+                    post.file.size as f64 / 1_048_576.0
+                )),
+            ]),
+            Line::from(vec![
+                label("Artists: "),
+                Span::raw(joined(&post.tags.artist)),
+            ]),
+            Line::from(vec![
+                label("Characters: "),
+                Span::raw(joined(&post.tags.character)),
+            ]),
+            Line::from(vec![
+                label("Species: "),
+                Span::raw(joined(&post.tags.species)),
+            ]),
+        ]
+    }
+}
+
+impl<'a> StatefulWidget for PostSummary<'a> {
+    type State = SelectionPreview;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .title(format!("Post #{}", self.post.id));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [image_area, info_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(7)]).areas(inner);
+        let mut image_area = media_notice::render_notice(self.post, image_area, buf);
+        state.image_area = image_area;
+
+        let message = match state.image_mut(self.post.id) {
+            Some(PreviewImage::Ready(protocol)) => {
+                protocol.try_advance();
+                if protocol.is_ready() {
+                    if let Some(status) = protocol.playback_status()
+                        && image_area.height > 1
+                    {
+                        let [picture_area, bar_area] =
+                            Layout::vertical([Constraint::Min(1), Constraint::Length(1)])
+                                .areas(image_area);
+                        PlaybackBar::new(status).render(bar_area, buf);
+                        image_area = picture_area;
+                    }
+
+                    protocol.set_area(image_area);
+                    if let Some(protocol) = protocol.current_protocol_mut() {
+                        let image = StatefulImage::new().resize(Resize::Fit(None));
+                        StatefulWidget::render(image, image_area, buf, protocol);
+                    }
+                    None
+                } else {
+                    protocol
+                        .error()
+                        .map(|e| format!("Failed to decode image: {}", e))
+                }
+            }
+            Some(PreviewImage::Failed(error)) => Some(error.clone()),
+            None => Some("Loading preview...".to_string()),
+        };
+
+        if let Some(message) = message {
+            Paragraph::new(message)
+                .style(Style::default().fg(Color::DarkGray))
+                .centered()
+                .wrap(Wrap { trim: true })
+                .render(image_area, buf);
+        }
+
+        Paragraph::new(self.info_lines())
+            .block(Block::default().borders(Borders::TOP))
+            .wrap(Wrap { trim: true })
+            .render(info_area, buf);
+    }
+}
@@ -1,14 +1,17 @@
 use {
-    crate::{anim::ImageProtocol, models::E6Post},
+    super::{media_notice, minimap::Minimap, playback_bar::PlaybackBar},
+    crate::{anim::ImageProtocol, models::E6Post, zoom::ZoomLevel},
     ratatui::{
         buffer::Buffer,
-        layout::Rect,
+        layout::{Constraint, Layout, Rect},
         style::{Color, Modifier, Style},
         widgets::{Block, Borders, Clear, Paragraph, StatefulWidget, Widget},
     },
     ratatui_image::{Resize, StatefulImage},
 };
 
+const MINIMAP_HEIGHT: u16 = 7;
+
 pub struct PostViewer<'a> {
     post: &'a E6Post,
 }
@@ -35,17 +38,77 @@ impl<'a> StatefulWidget for PostViewer<'a> {
                     .add_modifier(Modifier::BOLD),
             );
 
-        let inner = block.inner(area);
+        let mut inner = block.inner(area);
         block.render(area, buf);
+        inner = media_notice::render_notice(self.post, inner, buf);
 
         if let Some(image_protocol) = state {
             image_protocol.try_advance();
+        }
+
+        if let Some(image_protocol) = state
+            && image_protocol.is_ready()
+        {
+            if let Some(status) = image_protocol.playback_status()
+                && inner.height > 1
+            {
+                let [picture_area, bar_area] =
+                    Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(inner);
+                PlaybackBar::new(status).render(bar_area, buf);
+                inner = picture_area;
+            }
+
+            if let Some(zoom) = image_protocol.zoom_mut()
+                && zoom.level() != ZoomLevel::Fit
+                && inner.height > MINIMAP_HEIGHT
+            {
+                let minimap_width = Minimap::width_for(zoom.image_size(), MINIMAP_HEIGHT);
+                let [picture_area, bottom_area] =
+                    Layout::vertical([Constraint::Min(1), Constraint::Length(MINIMAP_HEIGHT)])
+                        .areas(inner);
+                let [status_area, minimap_area] =
+                    Layout::horizontal([Constraint::Min(0), Constraint::Length(minimap_width)])
+                        .areas(bottom_area);
+
+                if let Some(crop) = zoom.crop_for(picture_area) {
+                    let (width, height) = zoom.image_size();
+                    let status = format!(
+                        "Zoom {} | {}x{} at ({}, {}) of {}x{}",
+                        zoom.level().label(),
+                        crop.width,
+                        crop.height,
+                        crop.x,
+                        crop.y,
+                        width,
+                        height
+                    );
+                    Paragraph::new(status)
+                        .style(Style::default().fg(Color::DarkGray))
+                        .render(status_area, buf);
+                    Minimap::new(zoom.image_size(), crop, zoom.level()).render(minimap_area, buf);
+                }
+
+                match zoom.protocol_for(picture_area) {
+                    Some(protocol) => {
+                        let image_widget = StatefulImage::new().resize(Resize::Fit(None));
+                        StatefulWidget::render(image_widget, picture_area, buf, protocol);
+                    }
+                    None => Paragraph::new("Loading full resolution...")
+                        .style(Style::default().fg(Color::DarkGray))
+                        .centered()
+                        .render(picture_area, buf),
+                }
+                return;
+            }
 
-            let protocol = image_protocol.current_protocol_mut();
-            let image_widget = StatefulImage::new().resize(Resize::Fit(None));
-            StatefulWidget::render(image_widget, inner, buf, protocol);
+            image_protocol.set_area(inner);
+            if let Some(protocol) = image_protocol.current_protocol_mut() {
+                let image_widget = StatefulImage::new().resize(Resize::Fit(None));
+                StatefulWidget::render(image_widget, inner, buf, protocol);
+            }
         } else {
-            let placeholder = Paragraph::new("Loading image...")
+            let message = media_notice::placeholder_message(self.post, state.as_ref());
+            let placeholder = Paragraph::new(message)
                 .style(Style::default().fg(Color::DarkGray))
                 .centered();
             placeholder.render(inner, buf);
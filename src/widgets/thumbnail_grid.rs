@@ -0,0 +1,168 @@
+use {
+    super::media_notice,
+    crate::{
+        index::DownloadIndex,
+        models::E6Post,
+        thumbnails::{Thumbnail, Thumbnails},
+    },
+    ratatui::{
+        buffer::Buffer,
+        layout::{Position, Rect},
+        style::{Color, Modifier, Style},
+        text::{Line, Span},
+        widgets::{Block, Borders, Paragraph, StatefulWidget, Widget},
+    },
+    ratatui_image::{Resize, StatefulImage},
+    std::ops::Range,
+};
+
+const CELL_WIDTH: u16 = 22;
+const CELL_HEIGHT: u16 = 12;
+
+pub struct ThumbnailGridState {
+    pub thumbnails: Thumbnails,
+    pub columns: usize,
+    pub rows: usize,
+    pub offset_row: usize,
+    pub visible: Range<usize>,
+    pub area: Rect,
+}
+
+impl ThumbnailGridState {
+    pub fn new(thumbnails: Thumbnails) -> Self {
+        Self {
+            thumbnails,
+            columns: 1,
+            rows: 1,
+            offset_row: 0,
+            visible: 0..0,
+            area: Rect::default(),
+        }
+    }
+
+    pub fn index_at(&self, position: Position) -> Option<usize> {
+        if !self.area.contains(position) {
+            return None;
+        }
+
+        let cell_width = (self.area.width / self.columns as u16).max(1);
+        let cell_height = CELL_HEIGHT.min(self.area.height).max(1);
+        let column = ((position.x - self.area.x) / cell_width) as usize;
+        let row = ((position.y - self.area.y) / cell_height) as usize;
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+
+        let index = self.visible.start + row * self.columns + column;
+        (index < self.visible.end).then_some(index)
+    }
+}
+
+pub struct ThumbnailGrid<'a> {
+    posts: &'a [E6Post],
+    selected: Option<usize>,
+    download_index: &'a DownloadIndex,
+    title: String,
+}
+
+impl<'a> ThumbnailGrid<'a> {
+    pub fn new(
+        posts: &'a [E6Post],
+        selected: Option<usize>,
+        download_index: &'a DownloadIndex,
+        title: String,
+    ) -> Self {
+        Self {
+            posts,
+            selected,
+            download_index,
+            title,
+        }
+    }
+}
+
+impl<'a> StatefulWidget for ThumbnailGrid<'a> {
+    type State = ThumbnailGridState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let block = Block::default().borders(Borders::ALL).title(self.title);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        state.area = inner;
+
+        state.columns = (inner.width / CELL_WIDTH).max(1) as usize;
+        state.rows = (inner.height / CELL_HEIGHT).max(1) as usize;
+        let cell_width = inner.width / state.columns as u16;
+        let cell_height = CELL_HEIGHT.min(inner.height);
+
+        if let Some(selected) = self.selected {
+            let row = selected / state.columns;
+            if row < state.offset_row {
+                state.offset_row = row;
+            } else if row >= state.offset_row + state.rows {
+                state.offset_row = row + 1 - state.rows;
+            }
+        }
+
+        let start = (state.offset_row * state.columns).min(self.posts.len());
+        let end = (start + state.rows * state.columns).min(self.posts.len());
+        state.visible = start..end;
+
+        for (slot, index) in (start..end).enumerate() {
+            let post = &self.posts[index];
+            let cell = Rect {
+                x: inner.x + (slot % state.columns) as u16 * cell_width,
+                y: inner.y + (slot / state.columns) as u16 * cell_height,
+                width: cell_width,
+                height: cell_height,
+            };
+
+            let is_selected = self.selected == Some(index);
+            let border_style = if is_selected {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+
+            let downloaded = if self.download_index.contains(post) {
+                Span::styled("✔ ", Style::default().fg(Color::Green))
+            } else {
+                Span::raw("")
+            };
+            let footer = Line::from(vec![
+                downloaded,
+                Span::raw(format!(
+                    "{} {}",
+                    post.rating.to_uppercase(),
+                    post.score.total
+                )),
+            ]);
+
+            let cell_block = Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title(format!("#{}", post.id))
+                .title_bottom(footer);
+            let cell_inner = cell_block.inner(cell);
+            cell_block.render(cell, buf);
+            let cell_inner = media_notice::render_notice(post, cell_inner, buf);
+
+            match state.thumbnails.get_mut(post.id) {
+                Some(Thumbnail::Ready(protocol)) => {
+                    let image = StatefulImage::new().resize(Resize::Fit(None));
+                    StatefulWidget::render(image, cell_inner, buf, protocol.as_mut());
+                }
+                Some(Thumbnail::Failed) => Paragraph::new("No preview")
+                    .style(Style::default().fg(Color::DarkGray))
+                    .centered()
+                    .render(cell_inner, buf),
+                None => Paragraph::new("Loading...")
+                    .style(Style::default().fg(Color::DarkGray))
+                    .centered()
+                    .render(cell_inner, buf),
+            }
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use {
+    image::{DynamicImage, imageops::FilterType},
+    ratatui::layout::Rect,
+    ratatui_image::{Resize, ResizeEncodeRender, picker::Picker, protocol::StatefulProtocol},
+    std::{
+        sync::{
+            Arc,
+            mpsc::{self, Receiver, TryRecvError},
+        },
+        thread,
+    },
+};
+
+const PAN_STEP: f64 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ZoomLevel {
+    #[default]
+    Fit,
+    Actual,
+    Double,
+    Quadruple,
+}
+
+impl ZoomLevel {
+    fn scale(self) -> Option<u32> {
+        match self {
+            ZoomLevel::Fit => None,
+            ZoomLevel::Actual => Some(1),
+            ZoomLevel::Double => Some(2),
+            ZoomLevel::Quadruple => Some(4),
+        }
+    }
+
+    pub fn zoom_in(self) -> Self {
+        match self {
+            ZoomLevel::Fit => ZoomLevel::Actual,
+            ZoomLevel::Actual => ZoomLevel::Double,
+            ZoomLevel::Double | ZoomLevel::Quadruple => ZoomLevel::Quadruple,
+        }
+    }
+
+    pub fn zoom_out(self) -> Self {
+        match self {
+            ZoomLevel::Fit | ZoomLevel::Actual => ZoomLevel::Fit,
+            ZoomLevel::Double => ZoomLevel::Actual,
+            ZoomLevel::Quadruple => ZoomLevel::Double,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ZoomLevel::Fit => "Fit",
+            ZoomLevel::Actual => "1:1",
+            ZoomLevel::Double => "2x",
+            ZoomLevel::Quadruple => "4x",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct ZoomView {
+    size: (u32, u32),
+    source: Option<Arc<DynamicImage>>,
+    picker: Picker,
+    level: ZoomLevel,
+    center: (f64, f64),
+    rendered: Option<(Rect, Crop, StatefulProtocol)>,
+    pending: Option<(Rect, Crop, Receiver<StatefulProtocol>)>,
+}
+
+impl ZoomView {
+    pub fn new(size: (u32, u32), picker: Picker) -> Self {
+        Self {
+            size,
+            source: None,
+            picker,
+            level: ZoomLevel::Fit,
+            center: (0.5, 0.5),
+            rendered: None,
+            pending: None,
+        }
+    }
+
+    pub fn level(&self) -> ZoomLevel {
+        self.level
+    }
+
+    pub fn set_level(&mut self, level: ZoomLevel) {
+        self.level = level;
+        if level == ZoomLevel::Fit {
+            self.source = None;
+            self.rendered = None;
+            self.pending = None;
+        }
+    }
+
+    pub fn has_source(&self) -> bool {
+        self.source.is_some()
+    }
+
+    pub fn set_source(&mut self, source: Arc<DynamicImage>) {
+        if self.level == ZoomLevel::Fit {
+            return;
+        }
+        self.size = (source.width(), source.height());
+        self.source = Some(source);
+        self.rendered = None;
+        self.pending = None;
+    }
+
+    pub fn image_size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub fn pan(&mut self, dx: i32, dy: i32) {
+        let (width, height) = self.size;
+        let (view_width, view_height) = match self.rendered {
+            Some((_, crop, _)) => (crop.width, crop.height),
+            None => (width, height),
+        };
+
+        self.center.0 += dx as f64 * PAN_STEP * view_width as f64 / width.max(1) as f64;
+        self.center.1 += dy as f64 * PAN_STEP * view_height as f64 / height.max(1) as f64;
+        self.center = (self.center.0.clamp(0.0, 1.0), self.center.1.clamp(0.0, 1.0));
+    }
+
+    pub fn crop_for(&self, area: Rect) -> Option<Crop> {
+        let scale = self.level.scale()?;
+        let (width, height) = self.size;
+        let (font_width, font_height) = self.picker.font_size();
+
+        let view_width = (area.width as u32 * font_width as u32 / scale).clamp(1, width.max(1));
+        let view_height = (area.height as u32 * font_height as u32 / scale).clamp(1, height.max(1));
+
+        let origin = |center: f64, view: u32, total: u32| {
+            let start = center * total as f64 - view as f64 / 2.0;
+            start.clamp(0.0, total.saturating_sub(view) as f64).round() as u32
+        };
+
+        Some(Crop {
+            x: origin(self.center.0, view_width, width),
+            y: origin(self.center.1, view_height, height),
+            width: view_width,
+            height: view_height,
+        })
+    }
+
+    /// The last finished crop; a newer one is rendered in the background after a zoom or pan.
+    pub fn protocol_for(&mut self, area: Rect) -> Option<&mut StatefulProtocol> {
+        let crop = self.crop_for(area)?;
+        let scale = self.level.scale()?;
+
+        // Re-centre on what is actually visible so panning past an edge doesn't accumulate.
+        let (width, height) = self.size;
+        self.center = (
+            (crop.x as f64 + crop.width as f64 / 2.0) / width.max(1) as f64,
+            (crop.y as f64 + crop.height as f64 / 2.0) / height.max(1) as f64,
+        );
+
+        if let Some((pending_area, pending_crop, ref rx)) = self.pending {
+            match rx.try_recv() {
+                Ok(protocol) => {
+                    self.rendered = Some((pending_area, pending_crop, protocol));
+                    self.pending = None;
+                }
+                Err(TryRecvError::Disconnected) => self.pending = None,
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+
+        let current = |rendered: Option<(Rect, Crop)>| rendered == Some((area, crop));
+        let rendered = self.rendered.as_ref().map(|(a, c, _)| (*a, *c));
+        if self.pending.is_none()
+            && !current(rendered)
+            && let Some(ref source) = self.source
+        {
+            let (tx, rx) = mpsc::channel();
+            let source = source.clone();
+            let picker = self.picker.clone();
+            thread::spawn(move || {
+                let _ = tx.send(render_crop(&source, crop, scale, &picker, area));
+            });
+            self.pending = Some((area, crop, rx));
+        }
+
+        self.rendered.as_mut().map(|(_, _, protocol)| protocol)
+    }
+}
+
+fn render_crop(
+    source: &DynamicImage,
+    crop: Crop,
+    scale: u32,
+    picker: &Picker,
+    area: Rect,
+) -> StatefulProtocol {
+    let mut region = source.crop_imm(crop.x, crop.y, crop.width, crop.height);
+    if scale > 1 {
+        region = region.resize_exact(crop.width * scale, crop.height * scale, FilterType::Nearest);
+    }
+    let mut protocol = picker.new_resize_protocol(region);
+    protocol.resize_encode(&Resize::Fit(None), area);
+    protocol
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::time::Duration};
+
+    fn view() -> ZoomView {
+        ZoomView::new((100, 50), Picker::from_fontsize((10, 20)))
+    }
+
+    #[test]
+    fn crops_around_the_centre_and_clamps_to_edges() {
+        let mut zoom = view();
+        let area = Rect::new(0, 0, 4, 1);
+        assert_eq!(zoom.crop_for(area), None);
+
+        zoom.set_level(ZoomLevel::Double);
+        let crop = Crop {
+            x: 40,
+            y: 20,
+            width: 20,
+            height: 10,
+        };
+        assert_eq!(zoom.crop_for(area), Some(crop));
+
+        zoom.pan(-10, -10);
+        assert_eq!(zoom.crop_for(area), Some(Crop { x: 0, y: 0, ..crop }));
+    }
+
+    #[test]
+    fn renders_crops_from_the_source_in_the_background() {
+        let mut zoom = view();
+        let area = Rect::new(0, 0, 4, 1);
+        zoom.set_source(Arc::new(DynamicImage::new_rgb8(200, 100)));
+        assert!(!zoom.has_source(), "a source is only kept while zoomed in");
+
+        zoom.set_level(ZoomLevel::Actual);
+        assert!(zoom.protocol_for(area).is_none());
+        zoom.set_source(Arc::new(DynamicImage::new_rgb8(200, 100)));
+        assert_eq!(zoom.image_size(), (200, 100));
+
+        let mut ready = false;
+        for _ in 0..500 {
+            if zoom.protocol_for(area).is_some() {
+                ready = true;
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(ready);
+
+        zoom.set_level(ZoomLevel::Fit);
+        assert!(!zoom.has_source());
+    }
+}